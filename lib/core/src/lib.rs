@@ -6,8 +6,17 @@ use types::{
 
 pub mod types;
 
-pub fn sample(scene: &Scene, x: f64, y: f64) -> HDRColor {
-    let ray = scene.camera.ray(x, y);
+pub fn sample(scene: &Scene, x: f64, y: f64, time: f64) -> HDRColor {
+    scene
+        .camera
+        .rays(x, y, time)
+        .into_iter()
+        .fold(HDRColor::BLACK, |result, (ray, filter)| {
+            result + trace(scene, ray) * filter
+        })
+}
+
+fn trace(scene: &Scene, ray: Ray) -> HDRColor {
     if let Some(hit) = scene.test(ray) {
         let position = ray.origin + ray.direction * hit.distance + hit.normal * 1e-3;
         let mut result = scene.ambient_light * hit.albedo;
@@ -16,6 +25,7 @@ pub fn sample(scene: &Scene, x: f64, y: f64) -> HDRColor {
                 let shadow_ray = Ray {
                     origin: position,
                     direction,
+                    time: ray.time,
                 };
 
                 let shadow_hit = scene.test(shadow_ray);
@@ -0,0 +1,177 @@
+use super::{
+    math::{Aabb, Vec3},
+    rt::Ray,
+};
+
+const BIN_COUNT: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+
+enum BvhNodeKind {
+    Leaf {
+        start: usize,
+        count: usize,
+    },
+    Inner {
+        left: usize,
+        right: usize,
+        axis: usize,
+    },
+}
+
+struct BvhNode {
+    bounds: Aabb,
+    kind: BvhNodeKind,
+}
+
+/// Bounding volume hierarchy over a list of items, built with the binned surface area heuristic.
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    indices: Vec<usize>,
+}
+
+impl Bvh {
+    /// `bounds[i]` is the bounding box of the item `i` reported to [`Bvh::traverse`].
+    pub fn new(bounds: &[Aabb]) -> Bvh {
+        let mut bvh = Bvh {
+            nodes: Vec::new(),
+            indices: (0..bounds.len()).collect(),
+        };
+        if !bounds.is_empty() {
+            let centers: Vec<Vec3> = bounds.iter().map(Aabb::center).collect();
+            bvh.build(bounds, &centers, 0, bounds.len());
+        }
+        bvh
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.nodes.first().map(|n| n.bounds).unwrap_or(Aabb::EMPTY)
+    }
+
+    fn build(&mut self, bounds: &[Aabb], centers: &[Vec3], start: usize, end: usize) -> usize {
+        let node_bounds = self.indices[start..end]
+            .iter()
+            .fold(Aabb::EMPTY, |b, &i| b.union(bounds[i]));
+        let node = self.nodes.len();
+        self.nodes.push(BvhNode {
+            bounds: node_bounds,
+            kind: BvhNodeKind::Leaf {
+                start,
+                count: end - start,
+            },
+        });
+
+        let count = end - start;
+        if count <= MAX_LEAF_SIZE {
+            return node;
+        }
+
+        let center_bounds = Aabb::from_points(self.indices[start..end].iter().map(|&i| centers[i]));
+        let extent = center_bounds.max - center_bounds.min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        if extent[axis] <= 0.0 {
+            return node;
+        }
+
+        let bin_of = |i: usize| {
+            let relative = (centers[i][axis] - center_bounds.min[axis]) / extent[axis];
+            ((relative * BIN_COUNT as f64) as usize).min(BIN_COUNT - 1)
+        };
+
+        let mut bin_bounds = [Aabb::EMPTY; BIN_COUNT];
+        let mut bin_counts = [0usize; BIN_COUNT];
+        for &i in &self.indices[start..end] {
+            let bin = bin_of(i);
+            bin_bounds[bin] = bin_bounds[bin].union(bounds[i]);
+            bin_counts[bin] += 1;
+        }
+
+        // Cost of splitting after each bin, sweeping from the right first
+        let mut right_costs = [0.0; BIN_COUNT];
+        let mut right_bounds = Aabb::EMPTY;
+        let mut right_count = 0;
+        for bin in (1..BIN_COUNT).rev() {
+            right_bounds = right_bounds.union(bin_bounds[bin]);
+            right_count += bin_counts[bin];
+            right_costs[bin - 1] = right_bounds.surface_area() * right_count as f64;
+        }
+        let mut best_split = 0;
+        let mut best_cost = f64::INFINITY;
+        let mut left_bounds = Aabb::EMPTY;
+        let mut left_count = 0;
+        for bin in 0..BIN_COUNT - 1 {
+            left_bounds = left_bounds.union(bin_bounds[bin]);
+            left_count += bin_counts[bin];
+            let cost = left_bounds.surface_area() * left_count as f64 + right_costs[bin];
+            if cost < best_cost {
+                best_cost = cost;
+                best_split = bin;
+            }
+        }
+
+        let leaf_cost = node_bounds.surface_area() * count as f64;
+        if best_cost >= leaf_cost && count <= MAX_LEAF_SIZE * 4 {
+            return node;
+        }
+
+        let mut middle = start;
+        for i in start..end {
+            if bin_of(self.indices[i]) <= best_split {
+                self.indices.swap(i, middle);
+                middle += 1;
+            }
+        }
+        if middle == start || middle == end {
+            middle = start + count / 2;
+        }
+
+        let left = self.build(bounds, centers, start, middle);
+        let right = self.build(bounds, centers, middle, end);
+        self.nodes[node].kind = BvhNodeKind::Inner { left, right, axis };
+        node
+    }
+
+    /// Calls `visit` with every item whose bounds the ray hits before `limit`, nearest nodes first.
+    ///
+    /// `visit` receives the current limit and returns the new one, so nearest-hit queries
+    /// can shrink it to skip everything behind the closest hit found so far.
+    pub fn traverse(&self, ray: Ray, mut limit: f64, mut visit: impl FnMut(usize, f64) -> f64) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let origin: Vec3 = *ray.origin;
+        let direction: Vec3 = *ray.direction;
+        let inverse_direction = Vec3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z);
+
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            let (enter, exit) = node.bounds.hit_range(origin, inverse_direction);
+            if enter > exit || exit < 0.0 || enter > limit {
+                continue;
+            }
+            match node.kind {
+                BvhNodeKind::Leaf { start, count } => {
+                    for &i in &self.indices[start..start + count] {
+                        limit = visit(i, limit);
+                    }
+                }
+                BvhNodeKind::Inner { left, right, axis } => {
+                    if direction[axis] < 0.0 {
+                        stack.push(left);
+                        stack.push(right);
+                    } else {
+                        stack.push(right);
+                        stack.push(left);
+                    }
+                }
+            }
+        }
+    }
+}
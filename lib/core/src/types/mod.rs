@@ -1,2 +1,3 @@
+pub mod bvh;
 pub mod math;
 pub mod rt;
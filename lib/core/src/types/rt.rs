@@ -2,12 +2,16 @@ use std::sync::Arc;
 
 use seui_engine_raytracing_csg_renderer_types::{HDRColor, LDRColor};
 
-use super::math::{Direction, Position};
+use super::{
+    bvh::Bvh,
+    math::{Aabb, Direction, Position},
+};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Ray {
     pub origin: Position,
     pub direction: Direction,
+    pub time: f64,
 }
 
 #[derive(Clone, Debug)]
@@ -22,6 +26,9 @@ pub struct Hit {
 
 pub trait RTObject {
     fn test(&self, ray: Ray) -> Option<Hit>;
+
+    /// Box enclosing every possible hit, `None` for unbounded objects.
+    fn bounds(&self) -> Option<Aabb>;
 }
 
 pub trait Light {
@@ -29,21 +36,54 @@ pub trait Light {
 }
 
 pub trait Camera {
-    fn ray(&self, x: f64, y: f64) -> Ray;
+    /// `time` is the position within the shutter interval, from 0 (open) to 1 (close).
+    fn ray(&self, x: f64, y: f64, time: f64) -> Ray;
+
+    /// Rays contributing to the sample at `(x, y)`, each with the filter applied to its color.
+    fn rays(&self, x: f64, y: f64, time: f64) -> Vec<(Ray, HDRColor)> {
+        vec![(self.ray(x, y, time), HDRColor::default())]
+    }
 }
 
 pub struct Scene {
     pub camera: Box<dyn Camera + Send + Sync>,
-    pub objects: Vec<Box<dyn RTObject + Send + Sync>>,
     pub lights: Vec<Box<dyn Light + Send + Sync>>,
     pub sky_color: Arc<dyn Fn(Direction) -> HDRColor + Send + Sync>,
     pub ambient_light: HDRColor,
+    pub exposure: f64,
+    bounded: Vec<Box<dyn RTObject + Send + Sync>>,
+    bvh: Bvh,
+    unbounded: Vec<Box<dyn RTObject + Send + Sync>>,
 }
 
 impl Scene {
+    /// Builds the hierarchy over the bounded objects, unbounded ones are tested on every ray.
+    pub fn new(
+        camera: Box<dyn Camera + Send + Sync>,
+        objects: Vec<Box<dyn RTObject + Send + Sync>>,
+        lights: Vec<Box<dyn Light + Send + Sync>>,
+        sky_color: Arc<dyn Fn(Direction) -> HDRColor + Send + Sync>,
+        ambient_light: HDRColor,
+        exposure: f64,
+    ) -> Scene {
+        let (bounded, unbounded): (Vec<_>, Vec<_>) =
+            objects.into_iter().partition(|o| o.bounds().is_some());
+        let bounds: Vec<Aabb> = bounded.iter().filter_map(|o| o.bounds()).collect();
+        Scene {
+            camera,
+            lights,
+            sky_color,
+            ambient_light,
+            exposure,
+            bvh: Bvh::new(&bounds),
+            bounded,
+            unbounded,
+        }
+    }
+
     pub fn test(&self, ray: Ray) -> Option<Hit> {
         let mut result = None::<Hit>;
-        for object in self.objects.iter() {
+        for object in self.unbounded.iter() {
             result = match (result, object.test(ray)) {
                 (None, current) => current,
                 (previous, None) => previous,
@@ -56,6 +96,17 @@ impl Scene {
                 }
             }
         }
+
+        let limit = result.as_ref().map_or(f64::INFINITY, |hit| hit.distance);
+        self.bvh
+            .traverse(ray, limit, |i, limit| match self.bounded[i].test(ray) {
+                Some(hit) if hit.distance < limit => {
+                    let distance = hit.distance;
+                    result = Some(hit);
+                    distance
+                }
+                _ => limit,
+            });
         result
     }
 }
@@ -1,8 +1,13 @@
 use clap::Parser;
 use image::{Rgb, RgbImage};
 use rayon::prelude::*;
-use seui_engine_raytracing_csg_renderer_core::{sample, types::rt::Scene};
-use seui_engine_raytracing_csg_renderer_scene::{DeserializableScene, Image, ImageLoader};
+use seui_engine_raytracing_csg_renderer_core::{
+    sample,
+    types::rt::{Ray, Scene},
+};
+use seui_engine_raytracing_csg_renderer_scene::{
+    DeserializableScene, Image, ImageLoader, MeshLoader,
+};
 use seui_engine_raytracing_csg_renderer_types::{HDRColor, LDRColor};
 use std::{
     fs::File,
@@ -74,6 +79,7 @@ fn load_scene(scene_file: &str, scene_type: &Option<String>, screen_aspect_ratio
         .expect("Failed to read scene file");
 
     let image_loader = ImageImageLoader::new(scene_file);
+    let mesh_loader = FileMeshLoader::new(scene_file);
 
     match match scene_type {
         None => {
@@ -102,29 +108,28 @@ fn load_scene(scene_file: &str, scene_type: &Option<String>, screen_aspect_ratio
     } {
         SceneType::Jsonc => serde_jsonc2::from_str::<DeserializableScene>(&content_str)
             .expect("Failed to parse scene JSON")
-            .into_scene(screen_aspect_ratio, &image_loader),
+            .into_scene(screen_aspect_ratio, &image_loader, &mesh_loader),
         SceneType::Yaml => serde_yaml::from_str::<DeserializableScene>(&content_str)
             .expect("Failed to parse scene YAML")
-            .into_scene(screen_aspect_ratio, &image_loader),
+            .into_scene(screen_aspect_ratio, &image_loader, &mesh_loader),
         SceneType::Toml => toml::from_str::<DeserializableScene>(&content_str)
             .expect("Failed to parse scene TOML")
-            .into_scene(screen_aspect_ratio, &image_loader),
+            .into_scene(screen_aspect_ratio, &image_loader, &mesh_loader),
         SceneType::Json5 => json5::from_str::<DeserializableScene>(&content_str)
             .expect("Failed to parse scene JSON5")
-            .into_scene(screen_aspect_ratio, &image_loader),
+            .into_scene(screen_aspect_ratio, &image_loader, &mesh_loader),
         SceneType::Hjson => serde_hjson::from_str::<DeserializableScene>(&content_str)
             .expect("Failed to parse scene HJSON")
-            .into_scene(screen_aspect_ratio, &image_loader),
+            .into_scene(screen_aspect_ratio, &image_loader, &mesh_loader),
     }
 }
 
-fn tmp_hdr_to_ldr(color: HDRColor) -> LDRColor {
+fn tmp_hdr_to_ldr(color: HDRColor, exposure: f64) -> LDRColor {
     const GAMMA: f64 = 2.2;
-    const EXPOSURE: f64 = 1.0;
 
-    let r = 1.0 - (-color.r * EXPOSURE).exp();
-    let g = 1.0 - (-color.g * EXPOSURE).exp();
-    let b = 1.0 - (-color.b * EXPOSURE).exp();
+    let r = 1.0 - (-color.r * exposure).exp();
+    let g = 1.0 - (-color.g * exposure).exp();
+    let b = 1.0 - (-color.b * exposure).exp();
 
     LDRColor {
         r: r.powf(1.0 / GAMMA),
@@ -133,6 +138,37 @@ fn tmp_hdr_to_ldr(color: HDRColor) -> LDRColor {
     }
 }
 
+/// Per-pixel offset of the stratified shutter samples, so that neighbouring pixels
+/// don't sample the same instants and motion blur shows noise instead of banding.
+fn shutter_offset(x: usize, y: usize) -> f64 {
+    let mut hash = (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ (y as u64);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^= hash >> 31;
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn debug_sample(scene: &Scene, ray: Ray, args: &Args) -> HDRColor {
+    if let Some(hit) = scene.test(ray) {
+        let mut r = 1.0;
+        let mut g = 1.0;
+        let mut b = 1.0;
+        if args.normal {
+            r = hit.normal.x * 0.5 + 0.5;
+            g = hit.normal.y * 0.5 + 0.5;
+            b = hit.normal.z * 0.5 + 0.5;
+        }
+        if args.depth {
+            r /= hit.distance.sqrt();
+            g /= hit.distance.sqrt();
+            b /= hit.distance.sqrt();
+        }
+        HDRColor { r, g, b }
+    } else {
+        HDRColor::BLACK
+    }
+}
+
 fn main() {
     let args = Args::parse();
     rayon::ThreadPoolBuilder::new()
@@ -153,6 +189,11 @@ fn main() {
 
     let ss_factor = args.super_sampling;
     let inv_ss_factor = 1.0 / (ss_factor * ss_factor) as f64;
+    let exposure = if args.normal || args.depth {
+        1.0
+    } else {
+        scene.exposure
+    };
 
     let content: Vec<Vec<LDRColor>> = (0..args.height)
         .into_par_iter()
@@ -164,40 +205,31 @@ fn main() {
                         g: 0.0,
                         b: 0.0,
                     };
+                    let shutter_offset = shutter_offset(x, y);
                     for sy in 0..ss_factor {
                         for sx in 0..ss_factor {
+                            let sample_time =
+                                ((sy * ss_factor + sx) as f64 + shutter_offset) * inv_ss_factor;
                             let sample_x = (x as f64 + sx as f64 / ss_factor as f64)
                                 / (args.width as f64 - 1.0);
                             let sample_y = (y as f64 + sy as f64 / ss_factor as f64)
                                 / (args.height as f64 - 1.0);
                             color = color
                                 + match (args.normal, args.depth) {
-                                    (false, false) => sample(&scene, sample_x, sample_y),
-                                    _ => {
-                                        let ray = scene.camera.ray(sample_x, sample_y);
-                                        if let Some(hit) = scene.test(ray) {
-                                            let mut r = 1.0;
-                                            let mut g = 1.0;
-                                            let mut b = 1.0;
-                                            if args.normal {
-                                                r = hit.normal.x * 0.5 + 0.5;
-                                                g = hit.normal.y * 0.5 + 0.5;
-                                                b = hit.normal.z * 0.5 + 0.5;
-                                            }
-                                            if args.depth {
-                                                r /= hit.distance.sqrt();
-                                                g /= hit.distance.sqrt();
-                                                b /= hit.distance.sqrt();
-                                            }
-                                            HDRColor { r, g, b }
-                                        } else {
-                                            HDRColor::BLACK
-                                        }
+                                    (false, false) => {
+                                        sample(&scene, sample_x, sample_y, sample_time)
                                     }
+                                    _ => scene
+                                        .camera
+                                        .rays(sample_x, sample_y, sample_time)
+                                        .into_iter()
+                                        .fold(HDRColor::BLACK, |result, (ray, filter)| {
+                                            result + debug_sample(&scene, ray, &args) * filter
+                                        }),
                                 };
                         }
                     }
-                    tmp_hdr_to_ldr(color * inv_ss_factor)
+                    tmp_hdr_to_ldr(color * inv_ss_factor, exposure)
                 })
                 .collect()
         })
@@ -266,3 +298,26 @@ impl ImageLoader for ImageImageLoader {
         Arc::new(ImageImage::new(full_path.to_str().expect("Invalid path")))
     }
 }
+
+struct FileMeshLoader {
+    scene_dir: PathBuf,
+}
+
+impl FileMeshLoader {
+    fn new<P: AsRef<Path>>(scene_path: P) -> Self {
+        let scene_dir = scene_path
+            .as_ref()
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .to_path_buf();
+        FileMeshLoader { scene_dir }
+    }
+}
+
+impl MeshLoader for FileMeshLoader {
+    fn load(&self, path: &str) -> Vec<u8> {
+        let full_path = self.scene_dir.join(path);
+        std::fs::read(&full_path)
+            .unwrap_or_else(|e| panic!("Failed to read mesh {}: {}", full_path.display(), e))
+    }
+}
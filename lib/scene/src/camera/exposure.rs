@@ -0,0 +1,65 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::{deserialize::deserialize_positive_float, json_schema::float_positive};
+
+fn default_iso() -> f64 {
+    100.0
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ManualExposure {
+    #[serde(
+        default = "default_iso",
+        deserialize_with = "deserialize_positive_float"
+    )]
+    #[schemars(schema_with = "float_positive")]
+    iso: f64,
+    /// Shutter speed in seconds
+    #[serde(deserialize_with = "deserialize_positive_float")]
+    #[schemars(schema_with = "float_positive")]
+    shutter_speed: f64,
+    #[serde(deserialize_with = "deserialize_positive_float")]
+    #[schemars(schema_with = "float_positive")]
+    f_number: f64,
+    #[serde(default)]
+    compensation: f64,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Ev100Exposure {
+    ev100: f64,
+    #[serde(default)]
+    compensation: f64,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "camelCase", deny_unknown_fields)]
+pub enum Exposure {
+    Manual(ManualExposure),
+    Ev100(Ev100Exposure),
+}
+
+impl Exposure {
+    pub fn ev100(&self) -> f64 {
+        match self {
+            Exposure::Manual(e) => {
+                let ev100 =
+                    (e.f_number * e.f_number / e.shutter_speed).log2() - (e.iso / 100.0).log2();
+                ev100 - e.compensation
+            }
+            Exposure::Ev100(e) => e.ev100 - e.compensation,
+        }
+    }
+
+    /// Factor from scene luminance to the tone mapper's input, normalized so that
+    /// the luminance saturating the sensor maps to 1.
+    ///
+    /// Everything in the image is scaled, the sky and the ambient light included,
+    /// so they need realistic luminances like the lights need photometric units.
+    pub fn scale(&self) -> f64 {
+        1.0 / (1.2 * 2f64.powf(self.ev100()))
+    }
+}
@@ -2,19 +2,39 @@ use perspective::DeserializablePerspectiveCamera;
 use schemars::JsonSchema;
 use serde::Deserialize;
 use seui_engine_raytracing_csg_renderer_core::types::rt::Camera;
+use stereo::DeserializableStereoCamera;
 
+pub mod exposure;
 pub mod perspective;
+pub mod stereo;
 
 #[derive(Clone, Debug, Deserialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "camelCase", deny_unknown_fields)]
 pub enum DeserializableCamera {
     Perspective(DeserializablePerspectiveCamera),
+    Stereo(DeserializableStereoCamera),
 }
 
 impl DeserializableCamera {
+    pub fn exposure(&self) -> f64 {
+        match self {
+            DeserializableCamera::Perspective(c) => c.exposure(),
+            DeserializableCamera::Stereo(c) => c.exposure(),
+        }
+    }
+
+    /// Interval the times of the camera's rays fall in.
+    pub fn shutter(&self) -> (f64, f64) {
+        match self {
+            DeserializableCamera::Perspective(c) => c.shutter(),
+            DeserializableCamera::Stereo(c) => c.shutter(),
+        }
+    }
+
     pub fn into_camera(self, screen_aspect_ratio: f64) -> Box<dyn Camera + Send + Sync> {
         match self {
             DeserializableCamera::Perspective(c) => c.into_camera(screen_aspect_ratio),
+            DeserializableCamera::Stereo(c) => c.into_camera(screen_aspect_ratio),
         }
     }
 }
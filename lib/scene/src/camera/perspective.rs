@@ -5,6 +5,7 @@ use seui_engine_raytracing_csg_renderer_core::types::{
     rt::{Camera, Ray},
 };
 
+use super::exposure::Exposure;
 use crate::{
     deserialize::{deserialize_direction, deserialize_position},
     json_schema::{DirectionSchema, PositionSchema},
@@ -40,10 +41,28 @@ pub struct DeserializablePerspectiveCamera {
     #[serde(default = "forward", deserialize_with = "deserialize_direction")]
     #[schemars(with = "DirectionSchema")]
     direction: Direction,
+    #[serde(default)]
+    shutter_open: f64,
+    #[serde(default)]
+    shutter_close: f64,
+    #[serde(default)]
+    exposure: Option<Exposure>,
 }
 
 impl DeserializablePerspectiveCamera {
+    pub fn exposure(&self) -> f64 {
+        self.exposure.as_ref().map(Exposure::scale).unwrap_or(1.0)
+    }
+
+    pub fn shutter(&self) -> (f64, f64) {
+        if self.shutter_open > self.shutter_close {
+            panic!("The shutter must close no earlier than it opens");
+        }
+        (self.shutter_open, self.shutter_close)
+    }
+
     pub fn into_camera(self, screen_aspect_ratio: f64) -> Box<dyn Camera + Send + Sync> {
+        let (shutter_open, shutter_close) = self.shutter();
         let (tan_half_fov_x, tan_half_fov_y) = match self.fov_mode {
             FovMode::X => {
                 let tan_half_fov_x = (self.fov.to_radians() / 2.0).tan();
@@ -78,6 +97,8 @@ impl DeserializablePerspectiveCamera {
             direction: self.direction,
             right,
             up,
+            shutter_open,
+            shutter_close,
         })
     }
 }
@@ -89,10 +110,12 @@ struct PerspectiveCamera {
     direction: Direction,
     right: Vec3,
     up: Vec3,
+    shutter_open: f64,
+    shutter_close: f64,
 }
 
 impl Camera for PerspectiveCamera {
-    fn ray(&self, x: f64, y: f64) -> Ray {
+    fn ray(&self, x: f64, y: f64, time: f64) -> Ray {
         let dir_x = (2.0 * x - 1.0) * self.tan_half_fov_x;
         let dir_z = (1.0 - 2.0 * y) * self.tan_half_fov_y;
 
@@ -101,6 +124,7 @@ impl Camera for PerspectiveCamera {
         Ray {
             origin: self.position,
             direction,
+            time: self.shutter_open + (self.shutter_close - self.shutter_open) * time,
         }
     }
 }
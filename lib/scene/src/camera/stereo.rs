@@ -0,0 +1,185 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::Direction,
+    rt::{Camera, Ray},
+};
+use seui_engine_raytracing_csg_renderer_types::HDRColor;
+
+use crate::{
+    deserialize::{deserialize_nonnegative_float, deserialize_positive_float},
+    json_schema::float_positive,
+};
+
+use super::DeserializableCamera;
+
+fn default_interaxial() -> f64 {
+    0.065
+}
+
+fn default_convergence() -> f64 {
+    10.0
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ConvergenceMode {
+    ToeIn,
+    #[default]
+    OffAxis,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum StereoPacking {
+    #[default]
+    SideBySide,
+    OverUnder,
+    Anaglyph,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableStereoCamera {
+    camera: Box<DeserializableCamera>,
+    #[serde(
+        default = "default_interaxial",
+        deserialize_with = "deserialize_nonnegative_float"
+    )]
+    #[schemars(range(min = 0))]
+    interaxial: f64,
+    #[serde(
+        default = "default_convergence",
+        deserialize_with = "deserialize_positive_float"
+    )]
+    #[schemars(schema_with = "float_positive")]
+    convergence: f64,
+    #[serde(default)]
+    convergence_mode: ConvergenceMode,
+    #[serde(default)]
+    packing: StereoPacking,
+}
+
+impl DeserializableStereoCamera {
+    pub fn exposure(&self) -> f64 {
+        self.camera.exposure()
+    }
+
+    pub fn shutter(&self) -> (f64, f64) {
+        self.camera.shutter()
+    }
+
+    pub fn into_camera(self, screen_aspect_ratio: f64) -> Box<dyn Camera + Send + Sync> {
+        let eye_aspect_ratio = match self.packing {
+            StereoPacking::SideBySide => screen_aspect_ratio / 2.0,
+            StereoPacking::OverUnder => screen_aspect_ratio * 2.0,
+            StereoPacking::Anaglyph => screen_aspect_ratio,
+        };
+        let camera = self.camera.into_camera(eye_aspect_ratio);
+
+        // Recover the camera basis from its rays so that any camera can be wrapped
+        let forward = camera.ray(0.5, 0.5, 0.0).direction;
+        let to_right = *camera.ray(1.0, 0.5, 0.0).direction;
+        let right = Direction::new(to_right - *forward * to_right.dot(*forward));
+        let up = Direction::new(right.cross(*forward));
+
+        Box::new(StereoCamera {
+            camera,
+            half_interaxial: self.interaxial / 2.0,
+            convergence: self.convergence,
+            convergence_mode: self.convergence_mode,
+            packing: self.packing,
+            forward,
+            right,
+            up,
+        })
+    }
+}
+
+struct StereoCamera {
+    camera: Box<dyn Camera + Send + Sync>,
+    half_interaxial: f64,
+    convergence: f64,
+    convergence_mode: ConvergenceMode,
+    packing: StereoPacking,
+    forward: Direction,
+    right: Direction,
+    up: Direction,
+}
+
+impl StereoCamera {
+    /// `eye` is -1 for the left eye and 1 for the right eye.
+    fn eye_ray(&self, x: f64, y: f64, time: f64, eye: f64) -> Ray {
+        let ray = self.camera.ray(x, y, time);
+        let origin = ray.origin + self.right * (eye * self.half_interaxial);
+
+        let direction = match self.convergence_mode {
+            ConvergenceMode::OffAxis => {
+                // Keep the point on the convergence plane, shift the eye sideways
+                let distance = self.convergence / ray.direction.dot(self.forward);
+                let target = ray.origin + ray.direction * distance;
+                Direction::new(*(target - origin))
+            }
+            ConvergenceMode::ToeIn => {
+                // Rotate the whole eye around the up axis towards the convergence point
+                let angle = eye * (self.half_interaxial / self.convergence).atan();
+                let (sin, cos) = angle.sin_cos();
+                let k = *self.up;
+                let v = *ray.direction;
+                Direction::new(v * cos + k.cross(v) * sin + k * (k.dot(v) * (1.0 - cos)))
+            }
+        };
+
+        Ray {
+            origin,
+            direction,
+            time: ray.time,
+        }
+    }
+}
+
+impl Camera for StereoCamera {
+    fn ray(&self, x: f64, y: f64, time: f64) -> Ray {
+        match self.packing {
+            StereoPacking::SideBySide => {
+                if x < 0.5 {
+                    self.eye_ray(x * 2.0, y, time, -1.0)
+                } else {
+                    self.eye_ray(x * 2.0 - 1.0, y, time, 1.0)
+                }
+            }
+            StereoPacking::OverUnder => {
+                if y < 0.5 {
+                    self.eye_ray(x, y * 2.0, time, -1.0)
+                } else {
+                    self.eye_ray(x, y * 2.0 - 1.0, time, 1.0)
+                }
+            }
+            StereoPacking::Anaglyph => self.eye_ray(x, y, time, -1.0),
+        }
+    }
+
+    fn rays(&self, x: f64, y: f64, time: f64) -> Vec<(Ray, HDRColor)> {
+        match self.packing {
+            StereoPacking::Anaglyph => vec![
+                (
+                    self.eye_ray(x, y, time, -1.0),
+                    HDRColor {
+                        r: 1.0,
+                        g: 0.0,
+                        b: 0.0,
+                    },
+                ),
+                (
+                    self.eye_ray(x, y, time, 1.0),
+                    HDRColor {
+                        r: 0.0,
+                        g: 1.0,
+                        b: 1.0,
+                    },
+                ),
+            ],
+            _ => vec![(self.ray(x, y, time), HDRColor::default())],
+        }
+    }
+}
@@ -420,6 +420,55 @@ where
     deserializer.deserialize_any(F64Visitor)
 }
 
+pub fn deserialize_positive_float<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct F64Visitor;
+
+    impl Visitor<'_> for F64Visitor {
+        type Value = f64;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a number greater than 0")
+        }
+
+        fn visit_f64<E>(self, value: f64) -> Result<f64, E>
+        where
+            E: de::Error,
+        {
+            if value > 0.0 && value.is_finite() {
+                Ok(value)
+            } else {
+                Err(E::invalid_value(de::Unexpected::Float(value), &self))
+            }
+        }
+
+        fn visit_f32<E>(self, value: f32) -> Result<f64, E>
+        where
+            E: de::Error,
+        {
+            self.visit_f64(value as f64)
+        }
+
+        fn visit_i64<E>(self, value: i64) -> Result<f64, E>
+        where
+            E: de::Error,
+        {
+            self.visit_f64(value as f64)
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<f64, E>
+        where
+            E: de::Error,
+        {
+            self.visit_f64(value as f64)
+        }
+    }
+
+    deserializer.deserialize_any(F64Visitor)
+}
+
 pub fn deserialize_ldr_float<'de, D>(deserializer: D) -> Result<f64, D::Error>
 where
     D: Deserializer<'de>,
@@ -464,3 +513,35 @@ where
 
     deserializer.deserialize_any(F64Visitor)
 }
+
+pub fn deserialize_optional_ldr_color<'de, D>(deserializer: D) -> Result<Option<LDRColor>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_ldr_color(deserializer).map(Some)
+}
+
+pub fn deserialize_optional_ldr_float<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_ldr_float(deserializer).map(Some)
+}
+
+pub fn deserialize_optional_position<'de, D>(deserializer: D) -> Result<Option<Position>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_position(deserializer).map(Some)
+}
+
+pub fn deserialize_positions<'de, D>(deserializer: D) -> Result<Vec<Position>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    struct Wrapper(#[serde(deserialize_with = "deserialize_position")] Position);
+
+    let positions: Vec<Wrapper> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(positions.into_iter().map(|Wrapper(p)| p).collect())
+}
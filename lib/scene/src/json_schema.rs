@@ -415,6 +415,19 @@ fn float_nonnegative() -> Schema {
     })
 }
 
+/// Schema of numbers that must be greater than 0, for `schema_with`.
+pub fn float_positive(_gen: &mut SchemaGenerator) -> Schema {
+    Schema::Object(SchemaObject {
+        instance_type: Some(InstanceType::Number.into()),
+        format: Some("double".to_string()),
+        number: Some(Box::new(NumberValidation {
+            exclusive_minimum: Some(0.0),
+            ..Default::default()
+        })),
+        ..Default::default()
+    })
+}
+
 fn float_ldr() -> Schema {
     Schema::Object(SchemaObject {
         instance_type: Some(InstanceType::Number.into()),
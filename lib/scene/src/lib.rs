@@ -1,10 +1,17 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use camera::DeserializableCamera;
-use deserialize::deserialize_hdr_color;
+use deserialize::{deserialize_hdr_color, deserialize_nonnegative_float};
 use json_schema::HDRColorSchema;
 use light::DeserializableLight;
-use object::DeserializableRTObject;
+use mesh::MeshGeometry;
+use object::{
+    model::{util::one, DeserializableRTModel, RTModel},
+    DeserializableRTObject,
+};
 use schemars::JsonSchema;
 use serde::Deserialize;
 use seui_engine_raytracing_csg_renderer_core::types::rt::Scene;
@@ -14,6 +21,7 @@ pub mod camera;
 pub mod deserialize;
 pub mod json_schema;
 pub mod light;
+pub mod mesh;
 pub mod object;
 pub mod texture;
 
@@ -27,38 +35,65 @@ pub trait ImageLoader {
     fn load(&self, path: &str) -> Arc<dyn Image + Send + Sync>;
 }
 
+/// Provides the raw bytes of mesh and patch files, which are parsed according to their extension.
+pub trait MeshLoader {
+    fn load(&self, path: &str) -> Vec<u8>;
+}
+
 #[derive(Clone, Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct DeserializableScene {
     pub camera: DeserializableCamera,
+    #[serde(default)]
+    pub definitions: HashMap<String, DeserializableRTModel>,
     pub objects: Vec<DeserializableRTObject>,
     pub lights: Vec<DeserializableLight>,
     #[serde(deserialize_with = "deserialize_hdr_color")]
     #[schemars(with = "HDRColorSchema")]
     pub sky_color: HDRColor,
+    /// Luminance of the sky in cd/m² when the camera uses physical exposure
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    pub sky_luminance: f64,
     #[serde(deserialize_with = "deserialize_hdr_color")]
     #[schemars(with = "HDRColorSchema")]
     pub ambient_light: HDRColor,
+    /// Luminance in cd/m² of a white surface lit only by the ambient light,
+    /// when the camera uses physical exposure
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    pub ambient_luminance: f64,
 }
 
 impl DeserializableScene {
-    pub fn into_scene<T: ImageLoader>(self, screen_aspect_ratio: f64, image_loader: &T) -> Scene {
-        let mut cache = ImageCache::new(image_loader);
-        Scene {
-            camera: self.camera.into_camera(screen_aspect_ratio),
-            objects: self
-                .objects
+    pub fn into_scene<T: ImageLoader, M: MeshLoader>(
+        self,
+        screen_aspect_ratio: f64,
+        image_loader: &T,
+        mesh_loader: &M,
+    ) -> Scene {
+        let mut context = BuildContext {
+            image_cache: ImageCache::new(image_loader),
+            mesh_cache: MeshCache::new(mesh_loader),
+            model_cache: ModelCache::new(self.definitions),
+            shutter: self.camera.shutter(),
+        };
+        let exposure = self.camera.exposure();
+        let sky_color = self.sky_color * self.sky_luminance;
+        Scene::new(
+            self.camera.into_camera(screen_aspect_ratio),
+            self.objects
                 .into_iter()
-                .map(|o| o.into_rt_object(&mut cache))
+                .map(|o| o.into_rt_object(&mut context))
                 .collect(),
-            lights: self
-                .lights
+            self.lights
                 .into_iter()
                 .map(DeserializableLight::into_light)
                 .collect(),
-            sky_color: Arc::new(move |_| self.sky_color),
-            ambient_light: self.ambient_light,
-        }
+            Arc::new(move |_| sky_color),
+            self.ambient_light * self.ambient_luminance,
+            exposure,
+        )
     }
 }
 
@@ -86,3 +121,87 @@ impl<'a, T: ImageLoader> ImageCache<'a, T> {
         loaded_image
     }
 }
+
+pub struct MeshCache<'a, M: MeshLoader> {
+    loader: &'a M,
+    cache: HashMap<String, Arc<MeshGeometry>>,
+}
+
+impl<'a, M: MeshLoader> MeshCache<'a, M> {
+    pub fn new(loader: &'a M) -> MeshCache<'a, M> {
+        MeshCache {
+            loader,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn load(&mut self, path: &str) -> Arc<MeshGeometry> {
+        if let Some(mesh) = self.cache.get(path) {
+            return mesh.clone();
+        }
+
+        let mesh = mesh::parse(path, &self.loader.load(path))
+            .unwrap_or_else(|e| panic!("Failed to parse mesh \"{}\": {}", path, e));
+        let loaded_mesh = Arc::new(MeshGeometry::new(mesh));
+        self.cache.insert(path.to_string(), loaded_mesh.clone());
+
+        loaded_mesh
+    }
+
+    /// Raw bytes of a file next to the meshes, for models parsing their own formats.
+    pub fn read(&self, path: &str) -> Vec<u8> {
+        self.loader.load(path)
+    }
+}
+
+pub struct ModelCache {
+    definitions: HashMap<String, DeserializableRTModel>,
+    building: HashSet<String>,
+    cache: HashMap<String, Arc<dyn RTModel + Send + Sync>>,
+}
+
+impl ModelCache {
+    pub fn new(definitions: HashMap<String, DeserializableRTModel>) -> ModelCache {
+        ModelCache {
+            definitions,
+            building: HashSet::new(),
+            cache: HashMap::new(),
+        }
+    }
+}
+
+pub struct BuildContext<'a, T: ImageLoader, M: MeshLoader> {
+    pub image_cache: ImageCache<'a, T>,
+    pub mesh_cache: MeshCache<'a, M>,
+    pub model_cache: ModelCache,
+    /// Interval the times of the rays fall in
+    pub shutter: (f64, f64),
+}
+
+impl<T: ImageLoader, M: MeshLoader> BuildContext<'_, T, M> {
+    /// Builds the named definition once and shares it between all of its instances.
+    pub fn load_model(&mut self, name: &str) -> Arc<dyn RTModel + Send + Sync> {
+        if let Some(model) = self.model_cache.cache.get(name) {
+            return model.clone();
+        }
+        if self.model_cache.building.contains(name) {
+            panic!("Definition \"{}\" references itself", name);
+        }
+
+        let definition = self
+            .model_cache
+            .definitions
+            .get(name)
+            .unwrap_or_else(|| panic!("Unknown definition \"{}\"", name))
+            .clone();
+
+        self.model_cache.building.insert(name.to_string());
+        let model: Arc<dyn RTModel + Send + Sync> = definition.into_rt_model(self).into();
+        self.model_cache.building.remove(name);
+        self.model_cache
+            .cache
+            .insert(name.to_string(), model.clone());
+
+        model
+    }
+}
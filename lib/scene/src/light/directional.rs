@@ -9,8 +9,9 @@ use seui_engine_raytracing_csg_renderer_core::types::{
 use seui_engine_raytracing_csg_renderer_types::HDRColor;
 
 use crate::{
-    deserialize::{deserialize_direction, deserialize_hdr_color},
+    deserialize::{deserialize_direction, deserialize_hdr_color, deserialize_nonnegative_float},
     json_schema::{DirectionSchema, HDRColorSchema},
+    object::model::util::one,
 };
 
 fn down() -> Direction {
@@ -26,10 +27,18 @@ pub struct DirectionalLight {
     #[serde(default = "down", deserialize_with = "deserialize_direction")]
     #[schemars(with = "DirectionSchema")]
     direction: Direction,
+    /// Illuminance in lux when the camera uses physical exposure
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    illuminance: f64,
 }
 
 impl Light for DirectionalLight {
     fn test(&self, _position: Position) -> Option<(HDRColor, Direction, f64)> {
-        Some((self.color, -self.direction, f64::INFINITY))
+        Some((
+            self.color * self.illuminance,
+            -self.direction,
+            f64::INFINITY,
+        ))
     }
 }
@@ -7,8 +7,9 @@ use seui_engine_raytracing_csg_renderer_core::types::{
 use seui_engine_raytracing_csg_renderer_types::HDRColor;
 
 use crate::{
-    deserialize::{deserialize_hdr_color, deserialize_position},
+    deserialize::{deserialize_hdr_color, deserialize_nonnegative_float, deserialize_position},
     json_schema::{HDRColorSchema, PositionSchema},
+    object::model::util::one,
 };
 
 #[derive(Clone, Debug, Deserialize, JsonSchema)]
@@ -20,6 +21,10 @@ pub struct PointLight {
     #[serde(default, deserialize_with = "deserialize_hdr_color")]
     #[schemars(with = "HDRColorSchema")]
     color: HDRColor,
+    /// Luminous intensity in candela when the camera uses physical exposure
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    intensity: f64,
 }
 
 impl Light for PointLight {
@@ -27,13 +32,14 @@ impl Light for PointLight {
         // Compute the vector from the ray's origin to the light's position
         let to_light = self.position - position;
         let (direction, distance) = to_light.direction_and_length();
+        let color = self.color * self.intensity;
         if distance < 1e-3 {
-            return Some((self.color, direction, distance));
+            return Some((color, direction, distance));
         }
 
         // Compute attenuation using inverse square falloff
         let attenuation_factor = 1.0 / (distance * distance);
-        let attenuated_color = self.color * attenuation_factor;
+        let attenuated_color = color * attenuation_factor;
 
         Some((attenuated_color, direction, distance))
     }
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use seui_engine_raytracing_csg_renderer_core::types::{
+    bvh::Bvh,
+    math::{Aabb, Vec3},
+};
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+/// Indices of one triangle's corners into the vertex attribute lists of a [`Mesh`].
+#[derive(Clone, Debug)]
+pub struct Triangle {
+    pub positions: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub uvs: Option<[usize; 3]>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<[f64; 2]>,
+    /// Per-vertex colors, indexed like `positions` when present
+    pub colors: Vec<LDRColor>,
+    pub triangles: Vec<Triangle>,
+}
+
+/// Parses a mesh file, picking the format from the extension of `path`.
+pub fn parse(path: &str, bytes: &[u8]) -> Result<Mesh, String> {
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "obj" => obj::parse(std::str::from_utf8(bytes).map_err(|_| "Invalid OBJ file")?),
+        "stl" => stl::parse(bytes),
+        "ply" => ply::parse(bytes),
+        _ => Err(format!("Unsupported mesh format \"{}\"", extension)),
+    }
+}
+
+/// A loaded mesh with its acceleration structure, shared by every model using the same file.
+pub struct MeshGeometry {
+    pub mesh: Mesh,
+    pub bvh: Bvh,
+    /// Every edge is shared by exactly two triangles, so the mesh encloses a volume
+    pub closed: bool,
+}
+
+impl MeshGeometry {
+    pub fn new(mesh: Mesh) -> MeshGeometry {
+        let bounds: Vec<Aabb> = mesh
+            .triangles
+            .iter()
+            .map(|t| Aabb::from_points(t.positions.iter().map(|&i| mesh.positions[i])))
+            .collect();
+
+        let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+        for triangle in &mesh.triangles {
+            for i in 0..3 {
+                let a = triangle.positions[i];
+                let b = triangle.positions[(i + 1) % 3];
+                *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+        let closed = !edges.is_empty() && edges.values().all(|&count| count == 2);
+
+        MeshGeometry {
+            bvh: Bvh::new(&bounds),
+            mesh,
+            closed,
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use seui_engine_raytracing_csg_renderer_core::types::math::Vec3;
+
+use super::{Mesh, Triangle};
+
+/// Resolves a 1-based (or negative, counted from the end) OBJ index.
+fn resolve_index(index: &str, count: usize, line: usize) -> Result<usize, String> {
+    let index: i64 = index
+        .parse()
+        .map_err(|_| format!("Line {}: invalid index \"{}\"", line, index))?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!("Line {}: index {} out of range", line, index));
+    }
+    Ok(resolved as usize)
+}
+
+fn parse_floats<const N: usize>(
+    values: &mut dyn Iterator<Item = &str>,
+    line: usize,
+) -> Result<[f64; N], String> {
+    let mut result = [0.0; N];
+    for value in result.iter_mut() {
+        let token = values
+            .next()
+            .ok_or_else(|| format!("Line {}: expected {} numbers", line, N))?;
+        *value = token
+            .parse()
+            .map_err(|_| format!("Line {}: invalid number \"{}\"", line, token))?;
+    }
+    Ok(result)
+}
+
+/// Parses the geometry of a Wavefront OBJ file, triangulating polygons as fans.
+///
+/// Materials, groups and smoothing groups are ignored.
+pub fn parse(content: &str) -> Result<Mesh, String> {
+    let mut mesh = Mesh::default();
+
+    for (line_index, line) in content.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let [x, y, z] = parse_floats::<3>(&mut tokens, line_number)?;
+                mesh.positions.push(Vec3::new(x, y, z));
+            }
+            Some("vn") => {
+                let [x, y, z] = parse_floats::<3>(&mut tokens, line_number)?;
+                mesh.normals.push(Vec3::new(x, y, z));
+            }
+            Some("vt") => {
+                let [u] = parse_floats::<1>(&mut tokens, line_number)?;
+                let v = tokens.next().and_then(|t| t.parse().ok()).unwrap_or(0.0);
+                mesh.uvs.push([u, v]);
+            }
+            Some("f") => {
+                let mut corners = Vec::new();
+                for corner in tokens {
+                    let mut parts = corner.split('/');
+                    let position = resolve_index(
+                        parts.next().unwrap_or_default(),
+                        mesh.positions.len(),
+                        line_number,
+                    )?;
+                    let uv = match parts.next() {
+                        Some(uv) if !uv.is_empty() => {
+                            Some(resolve_index(uv, mesh.uvs.len(), line_number)?)
+                        }
+                        _ => None,
+                    };
+                    let normal = match parts.next() {
+                        Some(normal) if !normal.is_empty() => {
+                            Some(resolve_index(normal, mesh.normals.len(), line_number)?)
+                        }
+                        _ => None,
+                    };
+                    corners.push((position, uv, normal));
+                }
+                if corners.len() < 3 {
+                    return Err(format!(
+                        "Line {}: face needs at least 3 vertices",
+                        line_number
+                    ));
+                }
+
+                for i in 1..corners.len() - 1 {
+                    let [a, b, c] = [corners[0], corners[i], corners[i + 1]];
+                    mesh.triangles.push(Triangle {
+                        positions: [a.0, b.0, c.0],
+                        uvs: a.1.zip(b.1).zip(c.1).map(|((a, b), c)| [a, b, c]),
+                        normals: a.2.zip(b.2).zip(c.2).map(|((a, b), c)| [a, b, c]),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(mesh)
+}
@@ -0,0 +1,404 @@
+use seui_engine_raytracing_csg_renderer_core::types::math::Vec3;
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+use super::{Mesh, Triangle};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Result<Scalar, String> {
+        match name {
+            "char" | "int8" => Ok(Scalar::I8),
+            "uchar" | "uint8" => Ok(Scalar::U8),
+            "short" | "int16" => Ok(Scalar::I16),
+            "ushort" | "uint16" => Ok(Scalar::U16),
+            "int" | "int32" => Ok(Scalar::I32),
+            "uint" | "uint32" => Ok(Scalar::U32),
+            "float" | "float32" => Ok(Scalar::F32),
+            "double" | "float64" => Ok(Scalar::F64),
+            _ => Err(format!("Unknown PLY type \"{}\"", name)),
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    /// Maximum of integer color channels, which are normalized to `[0, 1]`.
+    fn color_scale(self) -> f64 {
+        match self {
+            Scalar::U8 => 255.0,
+            Scalar::U16 => 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+struct Property {
+    name: String,
+    scalar: Scalar,
+    /// Type of the length prefix for list properties
+    list: Option<Scalar>,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Reads values of the body one at a time, regardless of the encoding.
+struct Reader<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn read(&mut self, scalar: Scalar) -> Result<f64, String> {
+        if self.format == Format::Ascii {
+            let rest = &self.bytes[self.offset..];
+            let start = rest
+                .iter()
+                .position(|b| !b.is_ascii_whitespace())
+                .ok_or("Unexpected end of PLY data")?;
+            let length = rest[start..]
+                .iter()
+                .position(|b| b.is_ascii_whitespace())
+                .unwrap_or(rest.len() - start);
+            self.offset += start + length;
+            let token = std::str::from_utf8(&rest[start..start + length]).unwrap_or_default();
+            return token
+                .parse()
+                .map_err(|_| format!("Invalid PLY number \"{}\"", token));
+        }
+
+        let size = scalar.size();
+        let mut raw = self
+            .bytes
+            .get(self.offset..self.offset + size)
+            .ok_or("Unexpected end of PLY data")?
+            .to_vec();
+        self.offset += size;
+        if self.format == Format::BinaryLittleEndian {
+            raw.reverse();
+        }
+        // `raw` is big endian from here on
+        Ok(match scalar {
+            Scalar::I8 => raw[0] as i8 as f64,
+            Scalar::U8 => raw[0] as f64,
+            Scalar::I16 => i16::from_be_bytes([raw[0], raw[1]]) as f64,
+            Scalar::U16 => u16::from_be_bytes([raw[0], raw[1]]) as f64,
+            Scalar::I32 => i32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::U32 => u32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::F32 => f32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::F64 => f64::from_be_bytes([
+                raw[0], raw[1], raw[2], raw[3], raw[4], raw[5], raw[6], raw[7],
+            ]),
+        })
+    }
+}
+
+fn parse_header(bytes: &[u8]) -> Result<(Format, Vec<Element>, usize), String> {
+    if !bytes.starts_with(b"ply") {
+        return Err("Not a PLY file".to_string());
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut offset = 0;
+
+    loop {
+        let length = bytes[offset..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or("Unterminated PLY header")?;
+        let line = std::str::from_utf8(&bytes[offset..offset + length])
+            .map_err(|_| "Invalid PLY header")?
+            .trim();
+        offset += length + 1;
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", "ascii", _] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(Format::BinaryLittleEndian),
+            ["format", "binary_big_endian", _] => format = Some(Format::BinaryBigEndian),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| format!("Invalid PLY element count \"{}\"", count))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", length, scalar, name] => elements
+                .last_mut()
+                .ok_or("PLY property outside of an element")?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    scalar: Scalar::parse(scalar)?,
+                    list: Some(Scalar::parse(length)?),
+                }),
+            ["property", scalar, name] => elements
+                .last_mut()
+                .ok_or("PLY property outside of an element")?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    scalar: Scalar::parse(scalar)?,
+                    list: None,
+                }),
+            ["end_header"] => break,
+            _ => {}
+        }
+    }
+
+    Ok((format.ok_or("Missing PLY format")?, elements, offset))
+}
+
+/// Parses an ASCII or binary PLY file's `vertex` and `face` elements.
+///
+/// Vertex normals, texture coordinates and colors are read when present.
+pub fn parse(bytes: &[u8]) -> Result<Mesh, String> {
+    let (format, elements, offset) = parse_header(bytes)?;
+    let mut reader = Reader {
+        format,
+        bytes,
+        offset,
+    };
+    let mut mesh = Mesh::default();
+    let mut has_normals = false;
+    let mut has_uvs = false;
+    let mut faces = Vec::new();
+
+    for element in &elements {
+        let find = |names: &[&str]| {
+            element
+                .properties
+                .iter()
+                .position(|p| names.contains(&p.name.as_str()))
+        };
+        let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+        let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+        let uv = [
+            find(&["u", "s", "texture_u", "texture_s"]),
+            find(&["v", "t", "texture_v", "texture_t"]),
+        ];
+        let color = [
+            find(&["red", "r", "diffuse_red"]),
+            find(&["green", "g", "diffuse_green"]),
+            find(&["blue", "b", "diffuse_blue"]),
+        ];
+        let indices = find(&["vertex_indices", "vertex_index"]);
+
+        for _ in 0..element.count {
+            let mut values = Vec::with_capacity(element.properties.len());
+            let mut list = Vec::new();
+            for (i, property) in element.properties.iter().enumerate() {
+                match property.list {
+                    Some(length) => {
+                        let length = reader.read(length)? as usize;
+                        let items = (0..length)
+                            .map(|_| reader.read(property.scalar))
+                            .collect::<Result<Vec<f64>, String>>()?;
+                        if Some(i) == indices {
+                            list = items;
+                        }
+                        values.push(0.0);
+                    }
+                    None => values.push(reader.read(property.scalar)?),
+                }
+            }
+            let get = |index: Option<usize>| index.map(|i| values[i]);
+
+            match element.name.as_str() {
+                "vertex" => {
+                    let [x, y, z] = position.map(get);
+                    mesh.positions.push(Vec3::new(
+                        x.unwrap_or_default(),
+                        y.unwrap_or_default(),
+                        z.unwrap_or_default(),
+                    ));
+                    if let [Some(x), Some(y), Some(z)] = normal.map(get) {
+                        has_normals = true;
+                        mesh.normals.push(Vec3::new(x, y, z));
+                    }
+                    if let [Some(u), Some(v)] = uv.map(get) {
+                        has_uvs = true;
+                        mesh.uvs.push([u, v]);
+                    }
+                    let channel = |index: Option<usize>| {
+                        index.map(|i| {
+                            (values[i] / element.properties[i].scalar.color_scale()).clamp(0.0, 1.0)
+                        })
+                    };
+                    if let [Some(r), Some(g), Some(b)] = color.map(channel) {
+                        mesh.colors.push(LDRColor { r, g, b });
+                    }
+                }
+                "face" => faces.push(list),
+                _ => {}
+            }
+        }
+    }
+
+    // Faces may come before the vertices they index, so they are resolved last
+    for list in faces {
+        let mut corners = Vec::with_capacity(list.len());
+        for index in list {
+            if index < 0.0 || index.fract() != 0.0 || index >= mesh.positions.len() as f64 {
+                return Err(format!("PLY face index {} out of range", index));
+            }
+            corners.push(index as usize);
+        }
+        for i in 1..corners.len().saturating_sub(1) {
+            let positions = [corners[0], corners[i], corners[i + 1]];
+            mesh.triangles.push(Triangle {
+                positions,
+                normals: has_normals.then_some(positions),
+                uvs: has_uvs.then_some(positions),
+            });
+        }
+    }
+
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    const HEADER: &str = "element vertex 4\n\
+                          property float x\n\
+                          property float y\n\
+                          property float z\n\
+                          element face 1\n\
+                          property list uchar int vertex_indices\n\
+                          end_header\n";
+
+    const CORNERS: [[f32; 3]; 4] = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [0.0, 1.0, 0.5],
+    ];
+
+    /// A unit quad in the given encoding, split into two triangles when parsed.
+    fn quad(format: &str, f32_bytes: fn(f32) -> [u8; 4], i32_bytes: fn(i32) -> [u8; 4]) -> Vec<u8> {
+        let mut bytes = format!("ply\nformat {} 1.0\n{}", format, HEADER).into_bytes();
+        for corner in CORNERS {
+            for coordinate in corner {
+                bytes.extend(f32_bytes(coordinate));
+            }
+        }
+        bytes.push(4);
+        for index in 0..4 {
+            bytes.extend(i32_bytes(index));
+        }
+        bytes
+    }
+
+    fn assert_quad(bytes: &[u8]) {
+        let mesh = parse(bytes).unwrap();
+        assert_eq!(mesh.positions.len(), 4);
+        for (position, corner) in mesh.positions.iter().zip(CORNERS) {
+            assert_eq!(
+                [position.x, position.y, position.z],
+                corner.map(|c| c as f64)
+            );
+        }
+        let triangles: Vec<[usize; 3]> = mesh.triangles.iter().map(|t| t.positions).collect();
+        assert_eq!(triangles, [[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn parses_ascii() {
+        assert_quad(
+            b"ply\n\
+              format ascii 1.0\n\
+              comment a unit quad\n\
+              element vertex 4\n\
+              property float x\n\
+              property float y\n\
+              property float z\n\
+              element face 1\n\
+              property list uchar int vertex_indices\n\
+              end_header\n\
+              0 0 0\n\
+              1 0 0\n\
+              1 1 0\n\
+              0 1 0.5\n\
+              4 0 1 2 3\n",
+        );
+    }
+
+    #[test]
+    fn parses_binary_little_endian() {
+        assert_quad(&quad(
+            "binary_little_endian",
+            f32::to_le_bytes,
+            i32::to_le_bytes,
+        ));
+    }
+
+    #[test]
+    fn parses_binary_big_endian() {
+        assert_quad(&quad(
+            "binary_big_endian",
+            f32::to_be_bytes,
+            i32::to_be_bytes,
+        ));
+    }
+
+    #[test]
+    fn parses_faces_before_vertices() {
+        assert_quad(
+            b"ply\n\
+              format ascii 1.0\n\
+              element face 1\n\
+              property list uchar int vertex_indices\n\
+              element vertex 4\n\
+              property float x\n\
+              property float y\n\
+              property float z\n\
+              end_header\n\
+              4 0 1 2 3\n\
+              0 0 0\n\
+              1 0 0\n\
+              1 1 0\n\
+              0 1 0.5\n",
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_indices() {
+        for indices in ["0 1 4", "0 -1 2", "0 1.5 2"] {
+            let bytes = format!(
+                "ply\nformat ascii 1.0\n{}0 0 0\n1 0 0\n1 1 0\n0 1 0\n3 {}\n",
+                HEADER, indices
+            );
+            assert!(parse(bytes.as_bytes()).is_err(), "{}", indices);
+        }
+    }
+}
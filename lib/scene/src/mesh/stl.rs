@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use seui_engine_raytracing_csg_renderer_core::types::math::Vec3;
+
+use super::{Mesh, Triangle};
+
+/// STL stores every triangle's corners separately, so identical positions are merged
+/// to let closed meshes be recognized as such.
+struct Welder {
+    mesh: Mesh,
+    indices: HashMap<[u64; 3], usize>,
+}
+
+impl Welder {
+    fn vertex(&mut self, position: Vec3) -> usize {
+        let key = [position.x, position.y, position.z].map(f64::to_bits);
+        *self.indices.entry(key).or_insert_with(|| {
+            self.mesh.positions.push(position);
+            self.mesh.positions.len() - 1
+        })
+    }
+
+    fn triangle(&mut self, corners: [Vec3; 3]) {
+        let positions = corners.map(|c| self.vertex(c));
+        self.mesh.triangles.push(Triangle {
+            positions,
+            normals: None,
+            uvs: None,
+        });
+    }
+}
+
+fn is_binary(bytes: &[u8]) -> bool {
+    if bytes.len() < 84 {
+        return false;
+    }
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    // ASCII files start with "solid" too, but never match the binary size exactly
+    bytes.len() == 84 + count * 50
+}
+
+fn parse_binary(bytes: &[u8]) -> Mesh {
+    let mut welder = Welder {
+        mesh: Mesh::default(),
+        indices: HashMap::new(),
+    };
+    let read_f32 = |offset: usize| {
+        f32::from_le_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ]) as f64
+    };
+
+    for record in (84..bytes.len()).step_by(50) {
+        // Skip the stored face normal, it is recomputed from the winding
+        let corners = [1, 2, 3].map(|corner| {
+            let offset = record + corner * 12;
+            Vec3::new(read_f32(offset), read_f32(offset + 4), read_f32(offset + 8))
+        });
+        welder.triangle(corners);
+    }
+
+    welder.mesh
+}
+
+fn parse_ascii(content: &str) -> Result<Mesh, String> {
+    let mut welder = Welder {
+        mesh: Mesh::default(),
+        indices: HashMap::new(),
+    };
+    let mut corners = Vec::new();
+
+    for (line_index, line) in content.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("vertex") => {
+                let mut coordinates = [0.0; 3];
+                for coordinate in coordinates.iter_mut() {
+                    let token = tokens.next().unwrap_or_default();
+                    *coordinate = token.parse().map_err(|_| {
+                        format!("Line {}: invalid number \"{}\"", line_index + 1, token)
+                    })?;
+                }
+                corners.push(Vec3::new(coordinates[0], coordinates[1], coordinates[2]));
+            }
+            Some("endloop") => {
+                // Facets with more than three vertices are triangulated as fans
+                for i in 1..corners.len().saturating_sub(1) {
+                    welder.triangle([corners[0], corners[i], corners[i + 1]]);
+                }
+                corners.clear();
+            }
+            _ => {}
+        }
+    }
+
+    Ok(welder.mesh)
+}
+
+/// Parses an ASCII or binary STL file.
+pub fn parse(bytes: &[u8]) -> Result<Mesh, String> {
+    if is_binary(bytes) {
+        Ok(parse_binary(bytes))
+    } else {
+        let content = std::str::from_utf8(bytes).map_err(|_| "Invalid ASCII STL".to_string())?;
+        parse_ascii(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    /// Two triangles of a unit square sharing the diagonal from (0, 0, 0) to (1, 1, 0).
+    const SQUARE: [[[f32; 3]; 3]; 2] = [
+        [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]],
+        [[0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]],
+    ];
+
+    fn assert_square(bytes: &[u8]) {
+        let mesh = parse(bytes).unwrap();
+        // The shared corners are welded
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.triangles.len(), 2);
+        for (triangle, corners) in mesh.triangles.iter().zip(SQUARE) {
+            for (&index, corner) in triangle.positions.iter().zip(corners) {
+                let position = mesh.positions[index];
+                assert_eq!(
+                    [position.x, position.y, position.z],
+                    corner.map(|c| c as f64)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parses_ascii() {
+        assert_square(
+            b"solid square\n\
+              facet normal 0 0 1\n\
+              outer loop\n\
+              vertex 0 0 0\n\
+              vertex 1 0 0\n\
+              vertex 1 1 0\n\
+              endloop\n\
+              endfacet\n\
+              facet normal 0 0 1\n\
+              outer loop\n\
+              vertex 0 0 0\n\
+              vertex 1 1 0\n\
+              vertex 0 1 0\n\
+              endloop\n\
+              endfacet\n\
+              endsolid square\n",
+        );
+    }
+
+    #[test]
+    fn parses_binary() {
+        // Binary STL is always little endian
+        let mut bytes = b"solid square".to_vec();
+        bytes.resize(80, b' ');
+        bytes.extend(2u32.to_le_bytes());
+        for corners in SQUARE {
+            for coordinate in [[0.0, 0.0, 1.0]].into_iter().chain(corners).flatten() {
+                bytes.extend(coordinate.to_le_bytes());
+            }
+            bytes.extend([0, 0]);
+        }
+        assert_square(&bytes);
+    }
+}
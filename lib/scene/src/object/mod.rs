@@ -1,18 +1,39 @@
 use model::{
+    bezier_patch::DeserializableBezierPatch,
+    cone::DeserializableCone,
     csg::{DeserializableDifference, DeserializableIntersection, DeserializableUnion},
     cube::Cube,
+    cylinder::DeserializableCylinder,
+    extrude::DeserializableExtrude,
+    flat::{DeserializableDisk, DeserializableRectangle, DeserializableTriangle},
+    heightfield::DeserializableHeightfield,
+    implicit::DeserializableImplicit,
+    instance::DeserializableInstance,
+    lathe::DeserializableLathe,
+    mesh::DeserializableMesh,
+    metaballs::Metaballs,
+    motion::DeserializableMotion,
     plane::Plane,
+    polynomial::DeserializablePolynomial,
     quadratic::Quadratic,
     quadric::Quadric,
-    quartic::Quartic,
+    quartic::DeserializableQuartic,
+    sdf::DeserializableSdf,
+    smooth::{DeserializableSmoothOperation, SmoothOperator},
     sphere::DeserializableSphere,
+    superellipsoid::DeserializableSuperellipsoid,
+    torus::DeserializableTorus,
+    transform::DeserializableTransform,
     DeserializableRTModel, RTModel,
 };
 use schemars::JsonSchema;
 use serde::Deserialize;
-use seui_engine_raytracing_csg_renderer_core::types::rt::{Hit, RTObject, Ray};
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::Aabb,
+    rt::{Hit, RTObject, Ray},
+};
 
-use crate::{ImageCache, ImageLoader};
+use crate::{BuildContext, ImageLoader, MeshLoader};
 
 pub mod model;
 
@@ -22,39 +43,111 @@ pub enum DeserializableRTObject {
     Union(DeserializableUnion),
     Intersection(DeserializableIntersection),
     Difference(DeserializableDifference),
+    SmoothUnion(DeserializableSmoothOperation),
+    SmoothIntersection(DeserializableSmoothOperation),
+    SmoothDifference(DeserializableSmoothOperation),
+    Motion(DeserializableMotion),
+    Transform(DeserializableTransform),
+    Instance(DeserializableInstance),
     Sphere(DeserializableSphere),
     Plane(Plane),
     Cube(Cube),
+    Cylinder(DeserializableCylinder),
+    Cone(DeserializableCone),
     Quadric(Quadric),
     Quadratic(Quadratic),
-    Quartic(Quartic),
+    Quartic(DeserializableQuartic),
+    Polynomial(DeserializablePolynomial),
+    Implicit(DeserializableImplicit),
+    Torus(DeserializableTorus),
+    Mesh(DeserializableMesh),
+    Sdf(DeserializableSdf),
+    Metaballs(Metaballs),
+    Superellipsoid(DeserializableSuperellipsoid),
+    Heightfield(DeserializableHeightfield),
+    BezierPatch(DeserializableBezierPatch),
+    Lathe(DeserializableLathe),
+    Extrude(DeserializableExtrude),
+    Disk(DeserializableDisk),
+    Rectangle(DeserializableRectangle),
+    Triangle(DeserializableTriangle),
     Default(DeserializableDefaultRTObject),
 }
 
 impl DeserializableRTObject {
-    pub fn into_rt_object<T: ImageLoader>(
+    pub fn into_rt_object<T: ImageLoader, M: MeshLoader>(
         self,
-        image_cache: &mut ImageCache<T>,
+        context: &mut BuildContext<T, M>,
     ) -> Box<dyn RTObject + Send + Sync> {
         match self {
             DeserializableRTObject::Union(o) => {
-                Box::new(ModelRTObject::new(o.into_rt_model(image_cache)))
+                Box::new(ModelRTObject::new(o.into_rt_model(context)))
             }
             DeserializableRTObject::Intersection(o) => {
-                Box::new(ModelRTObject::new(o.into_rt_model(image_cache)))
+                Box::new(ModelRTObject::new(o.into_rt_model(context)))
             }
             DeserializableRTObject::Difference(o) => {
-                Box::new(ModelRTObject::new(o.into_rt_model(image_cache)))
+                Box::new(ModelRTObject::new(o.into_rt_model(context)))
+            }
+            DeserializableRTObject::SmoothUnion(o) => Box::new(ModelRTObject::new(
+                o.into_rt_model(SmoothOperator::Union, context),
+            )),
+            DeserializableRTObject::SmoothIntersection(o) => Box::new(ModelRTObject::new(
+                o.into_rt_model(SmoothOperator::Intersection, context),
+            )),
+            DeserializableRTObject::SmoothDifference(o) => Box::new(ModelRTObject::new(
+                o.into_rt_model(SmoothOperator::Difference, context),
+            )),
+            DeserializableRTObject::Motion(o) => {
+                Box::new(ModelRTObject::new(o.into_rt_model(context)))
+            }
+            DeserializableRTObject::Transform(o) => {
+                Box::new(ModelRTObject::new(o.into_rt_model(context)))
+            }
+            DeserializableRTObject::Instance(o) => {
+                Box::new(ModelRTObject::new(o.into_rt_model(context)))
             }
             DeserializableRTObject::Sphere(o) => {
-                Box::new(ModelRTObject::new(o.into_rt_model(image_cache)))
+                Box::new(ModelRTObject::new(o.into_rt_model(context)))
             }
             DeserializableRTObject::Plane(o) => Box::new(ModelRTObject::new(Box::new(o))),
             DeserializableRTObject::Cube(o) => Box::new(ModelRTObject::new(Box::new(o))),
+            DeserializableRTObject::Cylinder(o) => Box::new(ModelRTObject::new(o.into_rt_model())),
+            DeserializableRTObject::Cone(o) => Box::new(ModelRTObject::new(o.into_rt_model())),
             DeserializableRTObject::Quadric(o) => Box::new(ModelRTObject::new(Box::new(o))),
             DeserializableRTObject::Quadratic(o) => Box::new(ModelRTObject::new(Box::new(o))),
-            DeserializableRTObject::Quartic(o) => Box::new(ModelRTObject::new(Box::new(o))),
-            DeserializableRTObject::Default(o) => o.into_rt_object(image_cache),
+            DeserializableRTObject::Quartic(o) => Box::new(ModelRTObject::new(o.into_rt_model())),
+            DeserializableRTObject::Polynomial(o) => {
+                Box::new(ModelRTObject::new(o.into_rt_model()))
+            }
+            DeserializableRTObject::Implicit(o) => Box::new(ModelRTObject::new(o.into_rt_model())),
+            DeserializableRTObject::Torus(o) => Box::new(ModelRTObject::new(o.into_rt_model())),
+            DeserializableRTObject::Mesh(o) => {
+                Box::new(ModelRTObject::new(o.into_rt_model(context)))
+            }
+            DeserializableRTObject::Sdf(o) => Box::new(ModelRTObject::new(o.into_rt_model())),
+            DeserializableRTObject::Metaballs(o) => Box::new(ModelRTObject::new(o.into_rt_model())),
+            DeserializableRTObject::Superellipsoid(o) => {
+                Box::new(ModelRTObject::new(o.into_rt_model()))
+            }
+            DeserializableRTObject::Heightfield(o) => {
+                Box::new(ModelRTObject::new(o.into_rt_model(context)))
+            }
+            DeserializableRTObject::BezierPatch(o) => {
+                Box::new(ModelRTObject::new(o.into_rt_model(context)))
+            }
+            DeserializableRTObject::Lathe(o) => Box::new(ModelRTObject::new(o.into_rt_model())),
+            DeserializableRTObject::Extrude(o) => Box::new(ModelRTObject::new(o.into_rt_model())),
+            DeserializableRTObject::Disk(o) => {
+                Box::new(ModelRTObject::new(o.into_rt_model(context)))
+            }
+            DeserializableRTObject::Rectangle(o) => {
+                Box::new(ModelRTObject::new(o.into_rt_model(context)))
+            }
+            DeserializableRTObject::Triangle(o) => {
+                Box::new(ModelRTObject::new(o.into_rt_model(context)))
+            }
+            DeserializableRTObject::Default(o) => o.into_rt_object(context),
         }
     }
 }
@@ -80,6 +173,10 @@ impl RTObject for ModelRTObject {
             metallic: hit.metallic,
         })
     }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.model.bounds()
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, JsonSchema)]
@@ -89,10 +186,10 @@ pub struct DeserializableDefaultRTObject {
 }
 
 impl DeserializableDefaultRTObject {
-    pub fn into_rt_object<T: ImageLoader>(
+    pub fn into_rt_object<T: ImageLoader, M: MeshLoader>(
         self,
-        image_cache: &mut ImageCache<T>,
+        context: &mut BuildContext<T, M>,
     ) -> Box<dyn RTObject + Send + Sync> {
-        Box::new(ModelRTObject::new(self.model.into_rt_model(image_cache)))
+        Box::new(ModelRTObject::new(self.model.into_rt_model(context)))
     }
 }
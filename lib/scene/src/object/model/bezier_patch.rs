@@ -0,0 +1,605 @@
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    bvh::Bvh,
+    math::{Aabb, Direction, Position, Vec3},
+    rt::Ray,
+};
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+use crate::{
+    deserialize::{
+        deserialize_ldr_color, deserialize_ldr_float, deserialize_position, deserialize_positions,
+    },
+    json_schema::{LDRColorSchema, PositionSchema},
+    texture::{DeserializableTexture, Texture},
+    BuildContext, ImageLoader, MeshLoader,
+};
+
+use super::{
+    util::{enhance_normal, orthonormal_basis},
+    Hit, RTModel,
+};
+
+/// Four rows of four control points, each row running along `u`.
+type Patch = [Vec3; 16];
+
+/// Pieces each patch is cut into along both parameters for the acceleration structure.
+const SUBDIVISIONS: usize = 8;
+/// How many more times a piece is halved before it is taken for the triangles between its corners.
+const MAX_DEPTH: usize = 10;
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableBezierPatch {
+    /// 16 control points of a single bicubic patch, four rows of four along `u`
+    #[serde(default, deserialize_with = "deserialize_positions")]
+    #[schemars(with = "Vec<PositionSchema>")]
+    points: Vec<Position>,
+    /// BPT file listing bicubic patches, relative to the scene file, used instead of `points`
+    #[serde(default)]
+    path: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    /// The patches enclose a volume, so the model can be used as a CSG solid
+    #[serde(default)]
+    closed: bool,
+    #[serde(default, deserialize_with = "deserialize_ldr_color")]
+    #[schemars(with = "LDRColorSchema")]
+    albedo: LDRColor,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: f64,
+    /// Sampled with the patch parameters `u` and `v`
+    #[serde(default)]
+    texture: Option<DeserializableTexture>,
+}
+
+impl DeserializableBezierPatch {
+    pub fn into_rt_model<T: ImageLoader, M: MeshLoader>(
+        self,
+        context: &mut BuildContext<T, M>,
+    ) -> Box<dyn RTModel + Send + Sync> {
+        let patches = match (&self.path, self.points.len()) {
+            (Some(path), 0) => std::str::from_utf8(&context.mesh_cache.read(path))
+                .map_err(|_| "Invalid BPT file".to_string())
+                .and_then(parse_bpt)
+                .unwrap_or_else(|e| panic!("Failed to parse patches \"{}\": {}", path, e)),
+            (None, 16) => vec![std::array::from_fn(|i| *self.points[i])],
+            (Some(_), _) => panic!("A Bézier patch takes either points or a path"),
+            (None, count) => panic!("A Bézier patch needs 16 control points, got {}", count),
+        };
+
+        Box::new(BezierPatch::new(
+            patches,
+            self.position,
+            self.closed,
+            self.albedo,
+            self.roughness,
+            self.metallic,
+            self.texture
+                .map(|t| t.into_texture(&mut context.image_cache)),
+        ))
+    }
+}
+
+/// Parses the BPT format: the patch count, then the degrees `3 3` and 16 points per patch.
+fn parse_bpt(source: &str) -> Result<Vec<Patch>, String> {
+    let mut numbers = source.split_whitespace().map(|word| {
+        word.parse::<f64>()
+            .map_err(|_| format!("Invalid number \"{}\"", word))
+    });
+    let mut next = || {
+        numbers
+            .next()
+            .unwrap_or_else(|| Err("Unexpected end of file".to_string()))
+    };
+
+    let count = next()? as usize;
+    let mut patches = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (u_degree, v_degree) = (next()?, next()?);
+        if u_degree != 3.0 || v_degree != 3.0 {
+            return Err(format!(
+                "Only bicubic patches are supported, got degrees {} and {}",
+                u_degree, v_degree
+            ));
+        }
+        let mut patch = [Vec3::ZERO; 16];
+        for point in &mut patch {
+            *point = Vec3::new(next()?, next()?, next()?);
+        }
+        patches.push(patch);
+    }
+    Ok(patches)
+}
+
+/// Cubic Bernstein polynomials at `t` and their derivatives.
+fn bernstein(t: f64) -> ([f64; 4], [f64; 4]) {
+    let s = 1.0 - t;
+    (
+        [s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t],
+        [
+            -3.0 * s * s,
+            3.0 * s * (s - 2.0 * t),
+            3.0 * t * (2.0 * s - t),
+            3.0 * t * t,
+        ],
+    )
+}
+
+/// Point of the patch and its derivatives along `u` and `v`.
+fn evaluate(patch: &Patch, u: f64, v: f64) -> (Vec3, Vec3, Vec3) {
+    let (bu, du) = bernstein(u);
+    let (bv, dv) = bernstein(v);
+    let mut point = Vec3::ZERO;
+    let mut tangent_u = Vec3::ZERO;
+    let mut tangent_v = Vec3::ZERO;
+    for i in 0..4 {
+        for j in 0..4 {
+            let p = patch[4 * i + j];
+            point = point + p * (bv[i] * bu[j]);
+            tangent_u = tangent_u + p * (bv[i] * du[j]);
+            tangent_v = tangent_v + p * (dv[i] * bu[j]);
+        }
+    }
+    (point, tangent_u, tangent_v)
+}
+
+/// Control points of the part of a cubic curve between `lo` and `hi`, by de Casteljau's algorithm.
+fn restrict(curve: [Vec3; 4], lo: f64, hi: f64) -> [Vec3; 4] {
+    let split = |c: [Vec3; 4], t: f64| {
+        let lerp = |a: Vec3, b: Vec3| a * (1.0 - t) + b * t;
+        let (a, b, d) = (lerp(c[0], c[1]), lerp(c[1], c[2]), lerp(c[2], c[3]));
+        let (e, f) = (lerp(a, b), lerp(b, d));
+        let g = lerp(e, f);
+        ([c[0], a, e, g], [g, f, d, c[3]])
+    };
+    let (head, _) = split(curve, hi);
+    if lo <= 0.0 {
+        head
+    } else {
+        split(head, lo / hi).1
+    }
+}
+
+/// Control points of a part of the patch, four rows of four along `u`.
+fn piece_net(patch: &Patch, u: (f64, f64), v: (f64, f64)) -> Patch {
+    let rows: [[Vec3; 4]; 4] = std::array::from_fn(|i| {
+        restrict(
+            [
+                patch[4 * i],
+                patch[4 * i + 1],
+                patch[4 * i + 2],
+                patch[4 * i + 3],
+            ],
+            u.0,
+            u.1,
+        )
+    });
+    let columns: [[Vec3; 4]; 4] = std::array::from_fn(|j| {
+        restrict([rows[0][j], rows[1][j], rows[2][j], rows[3][j]], v.0, v.1)
+    });
+    std::array::from_fn(|k| columns[k % 4][k / 4])
+}
+
+/// Box around the control points of a part of the patch, which encloses the surface there.
+fn piece_bounds(patch: &Patch, u: (f64, f64), v: (f64, f64)) -> Aabb {
+    let bounds = Aabb::from_points(piece_net(patch, u, v));
+    // Flat pieces would give boxes without thickness
+    let margin = 1e-9 * (bounds.max - bounds.min).length().max(1.0);
+    let margin = Vec3::new(margin, margin, margin);
+    Aabb::new(bounds.min - margin, bounds.max + margin)
+}
+
+/// Whether the ray can meet the part of the patch with these control points, which enclose it.
+fn may_cross(net: &Patch, origin: Vec3, direction: Direction) -> bool {
+    let (n1, n2) = orthonormal_basis(direction);
+    let sides = |axis: Vec3| {
+        let mut offsets = net.iter().map(|&point| axis.dot(point - origin));
+        (offsets.clone().any(|o| o >= 0.0), offsets.any(|o| o <= 0.0))
+    };
+    let (ahead, _) = sides(*direction);
+    let (right, left) = sides(n1);
+    let (above, below) = sides(n2);
+    ahead && right && left && above && below
+}
+
+/// Whether the ray meets the part of the patch with these control points at most once.
+///
+/// Tangents along `u` and `v` are positive combinations of the differences of neighbouring
+/// control points. When every such pair turns the same way around the ray, no two points of
+/// the piece line up along it.
+fn is_one_to_one(net: &Patch, direction: Direction) -> bool {
+    let size = (0..16)
+        .map(|k| (net[k] - net[0]).length())
+        .fold(0.0, f64::max);
+    // Collapsed rows, like the pole of a lid, have no tangent to take into account
+    let tiny = 1e-9 * size;
+    let along_u: Vec<Vec3> = (0..12)
+        .map(|k| net[4 * (k / 3) + k % 3 + 1] - net[4 * (k / 3) + k % 3])
+        .filter(|tangent| tangent.length() > tiny)
+        .collect();
+    let along_v: Vec<Vec3> = (0..12)
+        .map(|k| net[k + 4] - net[k])
+        .filter(|tangent| tangent.length() > tiny)
+        .map(|tangent| tangent.cross(*direction))
+        .collect();
+    if along_u.is_empty() || along_v.is_empty() {
+        return false;
+    }
+    let mut turns = along_u
+        .iter()
+        .flat_map(|a| along_v.iter().map(move |b| a.dot(*b)));
+    turns.clone().all(|turn| turn > 0.0) || turns.all(|turn| turn < 0.0)
+}
+
+/// Normal of the patch, taken from just inside where an edge collapses to a point.
+fn normal(patch: &Patch, u: f64, v: f64) -> Vec3 {
+    let (_, tangent_u, tangent_v) = evaluate(patch, u, v);
+    let normal = tangent_u.cross(tangent_v);
+    if normal.length() >= 1e-12 {
+        return normal;
+    }
+    let (_, tangent_u, tangent_v) = evaluate(
+        patch,
+        u + (0.5 - u).signum() * 1e-6,
+        v + (0.5 - v).signum() * 1e-6,
+    );
+    tangent_u.cross(tangent_v)
+}
+
+/// Part of a patch, the unit of the acceleration structure.
+struct Piece {
+    patch: usize,
+    u: (f64, f64),
+    v: (f64, f64),
+}
+
+struct Crossing {
+    distance: f64,
+    u: f64,
+    v: f64,
+    normal: Vec3,
+    /// Whether the ray runs against the patch's `u × v` normal
+    entering: bool,
+}
+
+struct BezierPatch {
+    patches: Vec<Patch>,
+    pieces: Vec<Piece>,
+    bvh: Bvh,
+    position: Position,
+    closed: bool,
+    albedo: LDRColor,
+    roughness: f64,
+    metallic: f64,
+    texture: Option<Arc<dyn Texture + Send + Sync>>,
+}
+
+impl BezierPatch {
+    fn new(
+        patches: Vec<Patch>,
+        position: Position,
+        closed: bool,
+        albedo: LDRColor,
+        roughness: f64,
+        metallic: f64,
+        texture: Option<Arc<dyn Texture + Send + Sync>>,
+    ) -> BezierPatch {
+        let step = 1.0 / SUBDIVISIONS as f64;
+        let mut pieces = Vec::new();
+        for patch in 0..patches.len() {
+            for i in 0..SUBDIVISIONS {
+                for j in 0..SUBDIVISIONS {
+                    pieces.push(Piece {
+                        patch,
+                        u: (j as f64 * step, (j + 1) as f64 * step),
+                        v: (i as f64 * step, (i + 1) as f64 * step),
+                    });
+                }
+            }
+        }
+        let bounds: Vec<Aabb> = pieces
+            .iter()
+            .map(|piece| piece_bounds(&patches[piece.patch], piece.u, piece.v))
+            .collect();
+
+        BezierPatch {
+            bvh: Bvh::new(&bounds),
+            patches,
+            pieces,
+            position,
+            closed,
+            albedo,
+            roughness,
+            metallic,
+            texture,
+        }
+    }
+
+    /// Newton's method on the ray written as the meeting line of two planes, started at the
+    /// center of the piece and accepted only if it converges within it.
+    fn newton(
+        &self,
+        origin: Vec3,
+        direction: Direction,
+        patch: usize,
+        u_range: (f64, f64),
+        v_range: (f64, f64),
+    ) -> Option<Crossing> {
+        let points = &self.patches[patch];
+        let (n1, n2) = orthonormal_basis(direction);
+        let (d1, d2) = (n1.dot(origin), n2.dot(origin));
+
+        let mut u = 0.5 * (u_range.0 + u_range.1);
+        let mut v = 0.5 * (v_range.0 + v_range.1);
+        let mut converged = false;
+        for _ in 0..16 {
+            let (point, tangent_u, tangent_v) = evaluate(points, u, v);
+            let (f1, f2) = (n1.dot(point) - d1, n2.dot(point) - d2);
+            let (a, b) = (n1.dot(tangent_u), n1.dot(tangent_v));
+            let (c, d) = (n2.dot(tangent_u), n2.dot(tangent_v));
+            let determinant = a * d - b * c;
+            if determinant == 0.0 {
+                return None;
+            }
+            let step_u = (d * f1 - b * f2) / determinant;
+            let step_v = (a * f2 - c * f1) / determinant;
+            u -= step_u;
+            v -= step_v;
+            if step_u.abs() + step_v.abs() < 1e-12 {
+                converged = true;
+                break;
+            }
+            // Wandering far away, leave the piece to its neighbours or to subdivision
+            if !(-0.5..=1.5).contains(&u) || !(-0.5..=1.5).contains(&v) {
+                return None;
+            }
+        }
+
+        let tolerance = 1e-9;
+        if !converged
+            || u < u_range.0 - tolerance
+            || u > u_range.1 + tolerance
+            || v < v_range.0 - tolerance
+            || v > v_range.1 + tolerance
+        {
+            return None;
+        }
+        let (u, v) = (u.clamp(0.0, 1.0), v.clamp(0.0, 1.0));
+        let (point, _, _) = evaluate(points, u, v);
+        let residual = (n1.dot(point) - d1).abs() + (n2.dot(point) - d2).abs();
+        if residual > 1e-7 * point.length().max(1.0) {
+            return None;
+        }
+        let distance = (point - origin).dot(*direction);
+        if distance <= 0.0 {
+            return None;
+        }
+
+        let normal = normal(points, u, v);
+        Some(Crossing {
+            distance,
+            u,
+            v,
+            normal,
+            entering: direction.dot(Direction::new(normal)) < 0.0,
+        })
+    }
+
+    /// Crossings of the ray with the two triangles between the corners of a piece.
+    fn corner_crossings(
+        &self,
+        ray: Ray,
+        patch: usize,
+        net: &Patch,
+        u: (f64, f64),
+        v: (f64, f64),
+        crossings: &mut Vec<Crossing>,
+    ) {
+        let (origin, direction): (Vec3, Direction) = (*ray.origin, ray.direction);
+        let corners = [
+            (net[0], u.0, v.0),
+            (net[3], u.1, v.0),
+            (net[15], u.1, v.1),
+            (net[12], u.0, v.1),
+        ];
+        for [a, b, c] in [
+            [corners[0], corners[1], corners[2]],
+            [corners[0], corners[2], corners[3]],
+        ] {
+            let (edge1, edge2) = (b.0 - a.0, c.0 - a.0);
+            let p = direction.cross(edge2);
+            let determinant = edge1.dot(p);
+            if determinant == 0.0 {
+                continue;
+            }
+            let s = origin - a.0;
+            let q = s.cross(edge1);
+            let beta = s.dot(p) / determinant;
+            let gamma = q.dot(*direction) / determinant;
+            let distance = edge2.dot(q) / determinant;
+            if beta < 0.0 || gamma < 0.0 || beta + gamma > 1.0 || distance <= 0.0 {
+                continue;
+            }
+            let alpha = 1.0 - beta - gamma;
+            let (u, v) = (
+                alpha * a.1 + beta * b.1 + gamma * c.1,
+                alpha * a.2 + beta * b.2 + gamma * c.2,
+            );
+            let normal = normal(&self.patches[patch], u, v);
+            crossings.push(Crossing {
+                distance,
+                u,
+                v,
+                normal,
+                entering: direction.dot(Direction::new(normal)) < 0.0,
+            });
+        }
+    }
+
+    /// Halves the piece in both parameters until the ray can meet it at most once and
+    /// Newton's method finds that crossing, or the ray misses it.
+    ///
+    /// Stopping at the first crossing of a piece the ray could meet twice, or dropping a
+    /// crossing at the depth limit, would flip the parity of every later crossing.
+    fn search(
+        &self,
+        ray: Ray,
+        patch: usize,
+        u: (f64, f64),
+        v: (f64, f64),
+        depth: usize,
+        crossings: &mut Vec<Crossing>,
+    ) {
+        let origin: Vec3 = *ray.origin;
+        let net = piece_net(&self.patches[patch], u, v);
+        if !may_cross(&net, origin, ray.direction) {
+            return;
+        }
+        if is_one_to_one(&net, ray.direction) {
+            if let Some(crossing) = self.newton(origin, ray.direction, patch, u, v) {
+                crossings.push(crossing);
+                return;
+            }
+        }
+        if depth == MAX_DEPTH {
+            // Small enough to stand for the triangles between its corners, which it shares
+            // with its neighbours, so the crossings there still come in consistent pairs
+            self.corner_crossings(ray, patch, &net, u, v, crossings);
+            return;
+        }
+        let (u_middle, v_middle) = (0.5 * (u.0 + u.1), 0.5 * (v.0 + v.1));
+        for u in [(u.0, u_middle), (u_middle, u.1)] {
+            for v in [(v.0, v_middle), (v_middle, v.1)] {
+                self.search(ray, patch, u, v, depth + 1, crossings);
+            }
+        }
+    }
+
+    fn hit(&self, ray: Ray, crossing: &Crossing, is_front_face: bool) -> Hit {
+        let albedo = match &self.texture {
+            // Texture coordinates grow upwards, image rows downwards
+            Some(texture) => texture.get(
+                crossing.u.rem_euclid(1.0),
+                (1.0 - crossing.v).rem_euclid(1.0),
+            ),
+            None => self.albedo,
+        };
+        Hit {
+            distance: crossing.distance,
+            normal: enhance_normal(
+                ray.direction,
+                Direction::new(crossing.normal),
+                is_front_face,
+            ),
+            albedo,
+            is_front_face,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        }
+    }
+}
+
+impl RTModel for BezierPatch {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let origin = *(ray.origin - self.position);
+
+        let mut crossings = Vec::new();
+        let local_ray = Ray {
+            origin: Position::new(origin),
+            ..ray
+        };
+        self.bvh.traverse(local_ray, f64::INFINITY, |piece, limit| {
+            let piece = &self.pieces[piece];
+            self.search(local_ray, piece.patch, piece.u, piece.v, 0, &mut crossings);
+            limit
+        });
+        crossings.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+        // Roots on the border of two pieces or patches are found from both sides
+        crossings.dedup_by(|b, a| {
+            a.entering == b.entering
+                && (a.distance - b.distance).abs() <= 1e-7 * a.distance.max(1.0)
+        });
+
+        let mut result = Vec::new();
+        if !self.closed {
+            // Open surfaces have no inside, every crossing enters and leaves at once
+            for crossing in &crossings {
+                result.push(self.hit(ray, crossing, true));
+                result.push(self.hit(ray, crossing, false));
+            }
+            return result;
+        }
+
+        // Parity of the remaining crossings tells whether the ray starts inside
+        let mut is_front_face = crossings.len() % 2 == 1;
+        if is_front_face {
+            result.push(Hit {
+                distance: 0.0,
+                normal: -ray.direction,
+                albedo: self.albedo,
+                is_front_face: true,
+                roughness: self.roughness,
+                metallic: self.metallic,
+            });
+        }
+        for crossing in &crossings {
+            is_front_face = !is_front_face;
+            result.push(self.hit(ray, crossing, is_front_face));
+        }
+
+        result
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(self.bvh.bounds().translate(*self.position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use seui_engine_raytracing_csg_renderer_core::types::{
+        math::{Direction, Position, Vec3},
+        rt::Ray,
+    };
+
+    use super::BezierPatch;
+
+    #[test]
+    fn finds_both_crossings_of_a_cup() {
+        // Straight along `u`, a cup along `v` that a ray along y crosses twice
+        let y = [-1.0, -1.0 / 3.0, 1.0 / 3.0, 1.0];
+        let z = [1.0, -1.0, -1.0, 1.0];
+        let patch = std::array::from_fn(|k| Vec3::new((k % 4) as f64 / 3.0, y[k / 4], z[k / 4]));
+        let cup = BezierPatch::new(
+            vec![patch],
+            Position::new(Vec3::ZERO),
+            false,
+            Default::default(),
+            0.0,
+            0.0,
+            None,
+        );
+        let ray = Ray {
+            origin: Position::new(Vec3::new(0.3, -2.0, 0.0)),
+            direction: Direction::new(Vec3::Y),
+            time: 0.0,
+        };
+
+        let mut crossings = Vec::new();
+        cup.search(ray, 0, (0.0, 1.0), (0.0, 1.0), 0, &mut crossings);
+        crossings.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        assert_eq!(crossings.len(), 2);
+        // Symmetric around y = 0, two units ahead
+        assert!((crossings[0].distance + crossings[1].distance - 4.0).abs() < 1e-9);
+        assert!(crossings[0].entering != crossings[1].entering);
+    }
+}
@@ -0,0 +1,209 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Direction, Position, Vec3},
+    rt::Ray,
+};
+
+use crate::{
+    deserialize::{deserialize_nonnegative_float, deserialize_position, deserialize_scale},
+    json_schema::{PositionSchema, Scale},
+};
+
+use super::{
+    util::{enhance_normal, one},
+    Hit,
+};
+
+/// Region an unbounded surface is cut down to, relative to the model's position.
+///
+/// Behaves like an `intersection` with a `cube` or `sphere`, capping the solid where it is cut.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "camelCase", deny_unknown_fields)]
+pub enum ClipBounds {
+    Box(BoxBounds),
+    Sphere(SphereBounds),
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BoxBounds {
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    /// Half of the size along each axis
+    #[serde(default, deserialize_with = "deserialize_scale")]
+    scale: Scale,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SphereBounds {
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    radius: f64,
+}
+
+/// Part of a ray within the bounds, with the outward normals where it enters and leaves.
+pub struct Clip {
+    pub start: f64,
+    pub end: f64,
+    pub start_normal: Direction,
+    pub end_normal: Direction,
+}
+
+impl ClipBounds {
+    pub fn aabb(&self) -> Aabb {
+        let (position, extent) = match self {
+            ClipBounds::Box(bounds) => (
+                bounds.position,
+                Vec3::new(bounds.scale.x, bounds.scale.y, bounds.scale.z),
+            ),
+            ClipBounds::Sphere(bounds) => (
+                bounds.position,
+                Vec3::new(bounds.radius, bounds.radius, bounds.radius),
+            ),
+        };
+        Aabb::new(*position - extent, *position + extent)
+    }
+
+    /// Part of the ray, relative to the model, ahead of its origin and within the bounds.
+    fn clip(&self, origin: Vec3, direction: Direction) -> Option<Clip> {
+        let (enter, exit, enter_normal, exit_normal) = match self {
+            ClipBounds::Box(_) => {
+                let aabb = self.aabb();
+                let mut enter = (f64::NEG_INFINITY, Vec3::ZERO);
+                let mut exit = (f64::INFINITY, Vec3::ZERO);
+                for axis in 0..3 {
+                    if direction[axis] == 0.0 {
+                        if origin[axis] < aabb.min[axis] || origin[axis] > aabb.max[axis] {
+                            return None;
+                        }
+                        continue;
+                    }
+                    let normal = [Vec3::X, Vec3::Y, Vec3::Z][axis] * direction[axis].signum();
+                    let t1 = (aabb.min[axis] - origin[axis]) / direction[axis];
+                    let t2 = (aabb.max[axis] - origin[axis]) / direction[axis];
+                    if t1.min(t2) > enter.0 {
+                        enter = (t1.min(t2), -normal);
+                    }
+                    if t1.max(t2) < exit.0 {
+                        exit = (t1.max(t2), normal);
+                    }
+                }
+                (enter.0, exit.0, enter.1, exit.1)
+            }
+            ClipBounds::Sphere(bounds) => {
+                let center = origin - *bounds.position;
+                let b = center.dot(*direction);
+                let discriminant = b * b - center.dot(center) + bounds.radius * bounds.radius;
+                if discriminant < 0.0 {
+                    return None;
+                }
+                let (enter, exit) = (-b - discriminant.sqrt(), -b + discriminant.sqrt());
+                (
+                    enter,
+                    exit,
+                    center + *direction * enter,
+                    center + *direction * exit,
+                )
+            }
+        };
+        if enter > exit || exit < 0.0 {
+            return None;
+        }
+
+        Some(if enter > 0.0 {
+            Clip {
+                start: enter,
+                end: exit,
+                start_normal: Direction::new(enter_normal),
+                end_normal: Direction::new(exit_normal),
+            }
+        } else {
+            Clip {
+                start: 0.0,
+                end: exit,
+                start_normal: -direction,
+                end_normal: Direction::new(exit_normal),
+            }
+        })
+    }
+}
+
+/// Part of the ray to search for the surface at `position`, everything ahead without bounds.
+fn clip(bounds: &Option<ClipBounds>, position: Position, ray: Ray) -> Option<Clip> {
+    match bounds {
+        Some(bounds) => bounds.clip(*(ray.origin - position), ray.direction),
+        None => Some(Clip {
+            start: 0.0,
+            end: f64::INFINITY,
+            start_normal: -ray.direction,
+            end_normal: ray.direction,
+        }),
+    }
+}
+
+/// Hits of a solid at `position` cut down to `bounds`, capped where it is cut.
+///
+/// `internal_test` gives the crossings of the surface up to a distance along a ray,
+/// `is_negative` the sign of its equation at a ray's origin and `hit` makes the caps.
+/// With an `inside` point, the crossings between it and the ray tell whether the ray starts
+/// inside, otherwise the points where the equation is negative are inside.
+pub fn clipped_test(
+    bounds: &Option<ClipBounds>,
+    position: Position,
+    inside: Option<Position>,
+    ray: Ray,
+    internal_test: impl Fn(Ray, f64) -> Vec<Hit>,
+    is_negative: impl Fn(Ray) -> bool,
+    hit: impl Fn(f64, Direction, bool) -> Hit,
+) -> Vec<Hit> {
+    let Some(clip) = clip(bounds, position, ray) else {
+        return Vec::new();
+    };
+    let clipped = Ray {
+        origin: ray.origin + ray.direction * clip.start,
+        direction: ray.direction,
+        time: ray.time,
+    };
+
+    let inside = match inside {
+        Some(inside) => {
+            let (inside_direction, inside_length) =
+                (clipped.origin - inside).direction_and_length();
+            let ray = Ray {
+                origin: inside,
+                direction: inside_direction,
+                time: ray.time,
+            };
+            internal_test(ray, inside_length).len().is_multiple_of(2)
+        }
+        None => is_negative(clipped),
+    };
+
+    let mut result = Vec::new();
+
+    let mut is_front_face = false;
+    if inside {
+        is_front_face = true;
+        result.push(hit(clip.start, clip.start_normal, true));
+    }
+    for hit in internal_test(clipped, clip.end - clip.start) {
+        is_front_face = !is_front_face;
+        result.push(Hit {
+            distance: clip.start + hit.distance,
+            normal: enhance_normal(ray.direction, hit.normal, is_front_face),
+            is_front_face,
+            ..hit
+        });
+    }
+    if is_front_face {
+        result.push(hit(clip.end, clip.end_normal, false));
+    }
+
+    result
+}
@@ -0,0 +1,277 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Direction, Position, Vec3},
+    rt::Ray,
+};
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+use crate::{
+    deserialize::{
+        deserialize_direction, deserialize_ldr_color, deserialize_ldr_float,
+        deserialize_nonnegative_float, deserialize_position,
+    },
+    json_schema::{DirectionSchema, LDRColorSchema, PositionSchema},
+};
+
+use super::{
+    sdf::{Sdf, SdfMaterial},
+    util::{disk_bounds, one, orthonormal_basis, up, zero},
+    Hit, RTModel,
+};
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableCone {
+    /// Center of the cone, halfway between its caps
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    #[serde(default = "up", deserialize_with = "deserialize_direction")]
+    #[schemars(with = "DirectionSchema")]
+    axis: Direction,
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    height: f64,
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    bottom_radius: f64,
+    #[serde(default = "zero", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    top_radius: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_color")]
+    #[schemars(with = "LDRColorSchema")]
+    albedo: LDRColor,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: f64,
+}
+
+impl DeserializableCone {
+    pub fn into_rt_model(self) -> Box<dyn RTModel + Send + Sync> {
+        Box::new(self.into_frustum())
+    }
+
+    pub fn into_sdf(self) -> Box<dyn Sdf + Send + Sync> {
+        Box::new(self.into_frustum())
+    }
+
+    fn into_frustum(self) -> Frustum {
+        Frustum::new(
+            self.position,
+            self.axis,
+            self.height,
+            self.bottom_radius,
+            self.top_radius,
+            self.albedo,
+            self.roughness,
+            self.metallic,
+        )
+    }
+}
+
+/// Solid between two parallel circular caps, which covers both cylinders and (truncated) cones.
+pub struct Frustum {
+    bottom: Position,
+    u: Vec3,
+    v: Vec3,
+    axis: Direction,
+    height: f64,
+    bottom_radius: f64,
+    top_radius: f64,
+    albedo: LDRColor,
+    roughness: f64,
+    metallic: f64,
+}
+
+impl Frustum {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        position: Position,
+        axis: Direction,
+        height: f64,
+        bottom_radius: f64,
+        top_radius: f64,
+        albedo: LDRColor,
+        roughness: f64,
+        metallic: f64,
+    ) -> Frustum {
+        let (u, v) = orthonormal_basis(axis);
+        Frustum {
+            bottom: position + axis * (-height / 2.0),
+            u,
+            v,
+            axis,
+            height,
+            bottom_radius,
+            top_radius,
+            albedo,
+            roughness,
+            metallic,
+        }
+    }
+
+    fn hit(&self, distance: f64, normal: Vec3, is_front_face: bool) -> Hit {
+        Hit {
+            distance,
+            normal: Direction::new(normal),
+            albedo: self.albedo,
+            is_front_face,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        }
+    }
+}
+
+impl RTModel for Frustum {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let mut result = Vec::new();
+        if self.height <= 0.0 {
+            return result;
+        }
+
+        // Work in the local frame where the bottom cap is at z = 0 and the top cap at z = height
+        let origin = ray.origin - self.bottom;
+        let (ox, oy, oz) = (
+            origin.dot(self.u),
+            origin.dot(self.v),
+            origin.dot(*self.axis),
+        );
+        let direction: Vec3 = *ray.direction;
+        let (dx, dy, dz) = (
+            direction.dot(self.u),
+            direction.dot(self.v),
+            direction.dot(*self.axis),
+        );
+
+        // Radius grows linearly along the axis: r(z) = r0 + k * z
+        let r0 = self.bottom_radius;
+        let k = (self.top_radius - self.bottom_radius) / self.height;
+
+        // Every crossing of the surface as (distance, outward normal in the local frame)
+        let mut crossings: Vec<(f64, Vec3)> = Vec::with_capacity(4);
+
+        let a = dx * dx + dy * dy - k * k * dz * dz;
+        let b = 2.0 * (ox * dx + oy * dy - k * (r0 + k * oz) * dz);
+        let c = ox * ox + oy * oy - (r0 + k * oz).powi(2);
+        let side_roots = if a.abs() < 1e-12 {
+            if b.abs() < 1e-12 {
+                vec![]
+            } else {
+                vec![-c / b]
+            }
+        } else {
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant < 0.0 {
+                vec![]
+            } else {
+                // Numerically stable form avoiding cancellation
+                let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+                if q == 0.0 {
+                    vec![0.0]
+                } else {
+                    vec![q / a, c / q]
+                }
+            }
+        };
+        for t in side_roots {
+            let z = oz + t * dz;
+            let radius = r0 + k * z;
+            if (0.0..=self.height).contains(&z) && radius >= 0.0 {
+                let x = ox + t * dx;
+                let y = oy + t * dy;
+                crossings.push((t, Vec3::new(x, y, -k * radius)));
+            }
+        }
+
+        if dz.abs() > 1e-12 {
+            for (z, radius, normal) in [
+                (0.0, self.bottom_radius, -Vec3::Z),
+                (self.height, self.top_radius, Vec3::Z),
+            ] {
+                let t = (z - oz) / dz;
+                let x = ox + t * dx;
+                let y = oy + t * dy;
+                if x * x + y * y <= radius * radius {
+                    crossings.push((t, normal));
+                }
+            }
+        }
+
+        // The frustum is convex, so the outermost crossings bound the inside of the ray
+        let Some(&(t_min, n_min)) = crossings.iter().min_by(|a, b| a.0.total_cmp(&b.0)) else {
+            return result;
+        };
+        let &(t_max, n_max) = crossings.iter().max_by(|a, b| a.0.total_cmp(&b.0)).unwrap();
+        if t_max < 0.0 || t_max - t_min < 1e-9 {
+            return result;
+        }
+
+        let to_world = |n: Vec3| self.u * n.x + self.v * n.y + *self.axis * n.z;
+        if t_min < 0.0 {
+            result.push(Hit {
+                normal: -ray.direction,
+                ..self.hit(0.0, Vec3::X, true)
+            });
+        } else {
+            result.push(self.hit(t_min, to_world(n_min), true));
+        }
+        result.push(self.hit(t_max, to_world(n_max), false));
+
+        result
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let bottom = *self.bottom;
+        let top = bottom + *self.axis * self.height;
+        Some(
+            disk_bounds(bottom, self.axis, self.bottom_radius).union(disk_bounds(
+                top,
+                self.axis,
+                self.top_radius,
+            )),
+        )
+    }
+}
+
+impl Sdf for Frustum {
+    fn distance(&self, position: Vec3) -> f64 {
+        // Capped cone distance in the (radial, axial) half plane, centered between the caps
+        let p = position - *self.bottom;
+        let half_height = self.height / 2.0;
+        let x = (p.dot(self.u).powi(2) + p.dot(self.v).powi(2)).sqrt();
+        let y = p.dot(*self.axis) - half_height;
+        let (r1, r2) = (self.bottom_radius, self.top_radius);
+
+        let cap_radius = if y < 0.0 { r1 } else { r2 };
+        let ca = (x - x.min(cap_radius), y.abs() - half_height);
+        let (k1x, k1y) = (r2, half_height);
+        let (k2x, k2y) = (r2 - r1, self.height);
+        let k2_length_square = k2x * k2x + k2y * k2y;
+        let h = if k2_length_square > 0.0 {
+            (((k1x - x) * k2x + (k1y - y) * k2y) / k2_length_square).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let cb = (x - k1x + k2x * h, y - k1y + k2y * h);
+        let sign = if cb.0 < 0.0 && ca.1 < 0.0 { -1.0 } else { 1.0 };
+        sign * (ca.0 * ca.0 + ca.1 * ca.1)
+            .min(cb.0 * cb.0 + cb.1 * cb.1)
+            .sqrt()
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        RTModel::bounds(self)
+    }
+
+    fn material(&self, _position: Vec3) -> Option<SdfMaterial> {
+        Some(SdfMaterial {
+            albedo: self.albedo,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        })
+    }
+}
@@ -1,222 +1,336 @@
-use super::{DeserializableRTModel, Hit, RTModel};
-use crate::{ImageCache, ImageLoader};
+use super::{util::ray_hits_bounds, DeserializableRTModel, Hit, RTModel};
+use crate::{BuildContext, ImageLoader, MeshLoader};
+
+use std::{iter::Peekable, vec::IntoIter};
 
 use schemars::JsonSchema;
 use serde::Deserialize;
-use seui_engine_raytracing_csg_renderer_core::types::rt::Ray;
+use seui_engine_raytracing_csg_renderer_core::types::{math::Aabb, rt::Ray};
 
+/// Unites `a`, `b` and every one of `children`, whichever are given.
 #[derive(Clone, Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct DeserializableUnion {
-    a: Box<DeserializableRTModel>,
-    b: Box<DeserializableRTModel>,
+    #[serde(default)]
+    a: Option<Box<DeserializableRTModel>>,
+    #[serde(default)]
+    b: Option<Box<DeserializableRTModel>>,
+    #[serde(default)]
+    children: Vec<DeserializableRTModel>,
 }
 
 impl DeserializableUnion {
-    pub fn into_rt_model<T: ImageLoader>(
+    pub fn into_rt_model<T: ImageLoader, M: MeshLoader>(
         self,
-        image_cache: &mut ImageCache<T>,
+        context: &mut BuildContext<T, M>,
     ) -> Box<dyn RTModel + Send + Sync> {
         Box::new(Union {
-            a: self.a.into_rt_model(image_cache),
-            b: self.b.into_rt_model(image_cache),
+            children: build_operands("union", self.a, self.b, self.children, context),
         })
     }
 }
 
+/// Intersects `a`, `b` and every one of `children`, whichever are given.
 #[derive(Clone, Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct DeserializableIntersection {
-    a: Box<DeserializableRTModel>,
-    b: Box<DeserializableRTModel>,
+    #[serde(default)]
+    a: Option<Box<DeserializableRTModel>>,
+    #[serde(default)]
+    b: Option<Box<DeserializableRTModel>>,
+    #[serde(default)]
+    children: Vec<DeserializableRTModel>,
 }
 
 impl DeserializableIntersection {
-    pub fn into_rt_model<T: ImageLoader>(
+    pub fn into_rt_model<T: ImageLoader, M: MeshLoader>(
         self,
-        image_cache: &mut ImageCache<T>,
+        context: &mut BuildContext<T, M>,
     ) -> Box<dyn RTModel + Send + Sync> {
-        Box::new(Intersection {
-            a: self.a.into_rt_model(image_cache),
-            b: self.b.into_rt_model(image_cache),
-        })
+        let children = build_operands("intersection", self.a, self.b, self.children, context);
+        let bounds = children
+            .iter()
+            .filter_map(|child| child.bounds)
+            .reduce(Aabb::intersection);
+        Box::new(Intersection { children, bounds })
     }
 }
 
+/// Removes `b` and every one of `subtract` from `a`.
 #[derive(Clone, Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct DeserializableDifference {
     a: Box<DeserializableRTModel>,
-    b: Box<DeserializableRTModel>,
+    #[serde(default)]
+    b: Option<Box<DeserializableRTModel>>,
+    #[serde(default)]
+    subtract: Vec<DeserializableRTModel>,
 }
 
 impl DeserializableDifference {
-    pub fn into_rt_model<T: ImageLoader>(
+    pub fn into_rt_model<T: ImageLoader, M: MeshLoader>(
         self,
-        image_cache: &mut ImageCache<T>,
+        context: &mut BuildContext<T, M>,
     ) -> Box<dyn RTModel + Send + Sync> {
         Box::new(Difference {
-            a: self.a.into_rt_model(image_cache),
-            b: self.b.into_rt_model(image_cache),
+            base: Child::new(self.a.into_rt_model(context)),
+            subtracted: self
+                .b
+                .map(|b| *b)
+                .into_iter()
+                .chain(self.subtract)
+                .map(|model| Child::new(model.into_rt_model(context)))
+                .collect(),
         })
     }
 }
 
-fn remove_duplicate_hits(sorted: &mut Vec<Hit>) {
-    let mut result: Vec<Hit> = Vec::new();
-    for hit in sorted.drain(..) {
-        if let Some(last) = result.last() {
-            if last.is_front_face != hit.is_front_face
-                && (last.distance - hit.distance).abs() < 1e-6
-            {
-                result.pop();
-                continue;
-            }
-        }
-        result.push(hit);
+fn build_operands<T: ImageLoader, M: MeshLoader>(
+    operation: &str,
+    a: Option<Box<DeserializableRTModel>>,
+    b: Option<Box<DeserializableRTModel>>,
+    children: Vec<DeserializableRTModel>,
+    context: &mut BuildContext<T, M>,
+) -> Vec<Child> {
+    let operands: Vec<Child> = a
+        .into_iter()
+        .chain(b)
+        .map(|model| *model)
+        .chain(children)
+        .map(|model| Child::new(model.into_rt_model(context)))
+        .collect();
+    if operands.is_empty() {
+        panic!("The {} has no operands, give a, b or children", operation);
     }
-    *sorted = result;
+    operands
 }
 
-struct Union {
-    a: Box<dyn RTModel + Send + Sync>,
-    b: Box<dyn RTModel + Send + Sync>,
+/// Operand of a CSG node, skipped without being evaluated when the ray misses its bounds.
+struct Child {
+    model: Box<dyn RTModel + Send + Sync>,
+    bounds: Option<Aabb>,
 }
 
-impl RTModel for Union {
+impl Child {
+    fn new(model: Box<dyn RTModel + Send + Sync>) -> Child {
+        Child {
+            bounds: model.bounds(),
+            model,
+        }
+    }
+
     fn test(&self, ray: Ray) -> Vec<Hit> {
-        let mut a_hits = self.a.test(ray);
-        let mut b_hits = self.b.test(ray);
+        match &self.bounds {
+            Some(bounds) if !ray_hits_bounds(bounds, ray) => Vec::new(),
+            _ => self.model.test(ray),
+        }
+    }
+}
 
-        if a_hits.is_empty() {
-            return b_hits;
+/// Adds `hit` after the hits before it, unless it closes a piece of no length with the last one.
+fn push_hit(hits: &mut Vec<Hit>, hit: Hit) {
+    if let Some(last) = hits.last() {
+        if last.is_front_face != hit.is_front_face && (last.distance - hit.distance).abs() < 1e-6 {
+            hits.pop();
+            return;
         }
-        if b_hits.is_empty() {
-            return a_hits;
+    }
+    hits.push(hit);
+}
+
+/// Hits of every list in ascending distance, with the index of the list each comes from.
+///
+/// The lists are sorted already, so they are merged instead of collected and sorted again.
+/// Ties go to the earlier list.
+struct Merge {
+    lists: Vec<Peekable<IntoIter<Hit>>>,
+}
+
+impl Merge {
+    fn new(lists: Vec<Vec<Hit>>) -> Merge {
+        Merge {
+            lists: lists
+                .into_iter()
+                .map(|hits| hits.into_iter().peekable())
+                .collect(),
         }
+    }
+}
 
-        let mut all_hits = Vec::new();
-        all_hits.append(&mut a_hits);
-        all_hits.append(&mut b_hits);
-        all_hits.sort_by(|h1, h2| h1.distance.partial_cmp(&h2.distance).unwrap());
+impl Iterator for Merge {
+    type Item = (usize, Hit);
 
-        remove_duplicate_hits(&mut all_hits);
+    fn next(&mut self) -> Option<(usize, Hit)> {
+        let mut first: Option<(usize, f64)> = None;
+        for (i, list) in self.lists.iter_mut().enumerate() {
+            if let Some(hit) = list.peek() {
+                if first.is_none_or(|(_, distance)| hit.distance < distance) {
+                    first = Some((i, hit.distance));
+                }
+            }
+        }
+        let (i, _) = first?;
+        self.lists[i].next().map(|hit| (i, hit))
+    }
+}
 
-        let mut stack = 0;
+struct Union {
+    children: Vec<Child>,
+}
+
+impl RTModel for Union {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let mut hit_lists: Vec<Vec<Hit>> = self
+            .children
+            .iter()
+            .map(|child| child.test(ray))
+            .filter(|hits| !hits.is_empty())
+            .collect();
+        if hit_lists.len() <= 1 {
+            return hit_lists.pop().unwrap_or_default();
+        }
+
+        let mut depth = 0;
         let mut result = Vec::new();
-        for hit in all_hits {
+        for (_, hit) in Merge::new(hit_lists) {
             if hit.is_front_face {
-                if stack == 0 {
-                    result.push(hit);
+                if depth == 0 {
+                    push_hit(&mut result, hit);
                 }
-                stack += 1;
+                depth += 1;
             } else {
-                stack -= 1;
-                if stack == 0 {
-                    result.push(hit);
+                depth -= 1;
+                if depth == 0 {
+                    push_hit(&mut result, hit);
                 }
             }
         }
 
         result
     }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.children.iter().try_fold(Aabb::EMPTY, |bounds, child| {
+            Some(bounds.union(child.bounds?))
+        })
+    }
 }
 
 struct Intersection {
-    a: Box<dyn RTModel + Send + Sync>,
-    b: Box<dyn RTModel + Send + Sync>,
+    children: Vec<Child>,
+    /// Overlap of the bounded children's boxes.
+    bounds: Option<Aabb>,
 }
 
 impl RTModel for Intersection {
     fn test(&self, ray: Ray) -> Vec<Hit> {
-        let mut a_hits = self.a.test(ray);
-        if a_hits.is_empty() {
-            return a_hits;
+        // The children's boxes may each be hit while their overlap is not
+        if let Some(bounds) = &self.bounds {
+            if !ray_hits_bounds(bounds, ray) {
+                return Vec::new();
+            }
         }
 
-        let mut b_hits = self.b.test(ray);
-        if b_hits.is_empty() {
-            return b_hits;
+        let mut hit_lists = Vec::with_capacity(self.children.len());
+        for child in &self.children {
+            let hits = child.test(ray);
+            if hits.is_empty() {
+                return hits;
+            }
+            hit_lists.push(hits);
+        }
+        if hit_lists.len() == 1 {
+            return hit_lists.pop().unwrap();
         }
 
-        let mut all_hits = Vec::new();
-        all_hits.append(&mut a_hits);
-        all_hits.append(&mut b_hits);
-        all_hits.sort_by(|h1, h2| h1.distance.partial_cmp(&h2.distance).unwrap());
-
-        remove_duplicate_hits(&mut all_hits);
-
-        let mut stack = 0;
+        // Inside the intersection while inside every child
+        let count = hit_lists.len();
+        let mut depth = 0;
         let mut result = Vec::new();
-        for hit in all_hits {
+        for (_, hit) in Merge::new(hit_lists) {
             if hit.is_front_face {
-                stack += 1;
-                if stack == 2 {
-                    result.push(hit);
+                depth += 1;
+                if depth == count {
+                    push_hit(&mut result, hit);
                 }
             } else {
-                if stack == 2 {
-                    result.push(hit);
+                if depth == count {
+                    push_hit(&mut result, hit);
                 }
-                stack -= 1;
+                depth -= 1;
             }
         }
 
         result
     }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
 }
 
 struct Difference {
-    a: Box<dyn RTModel + Send + Sync>,
-    b: Box<dyn RTModel + Send + Sync>,
+    base: Child,
+    subtracted: Vec<Child>,
 }
 
 impl RTModel for Difference {
     fn test(&self, ray: Ray) -> Vec<Hit> {
-        let mut a_hits = self.a.test(ray);
-        if a_hits.is_empty() {
-            return a_hits;
+        let base_hits = self.base.test(ray);
+        if base_hits.is_empty() {
+            return base_hits;
         }
 
-        let mut b_hits = self.b.test(ray);
-        if b_hits.is_empty() {
-            return a_hits;
+        let mut hit_lists = Vec::with_capacity(self.subtracted.len() + 1);
+        for child in &self.subtracted {
+            let hits = child.test(ray);
+            if !hits.is_empty() {
+                hit_lists.push(hits);
+            }
         }
+        if hit_lists.is_empty() {
+            return base_hits;
+        }
+        // Last, so that at equal distances the subtracted hits come first
+        hit_lists.push(base_hits);
+        let base = hit_lists.len() - 1;
 
-        let mut all_hits = Vec::new();
-        all_hits.append(&mut a_hits.clone());
-        all_hits.append(&mut a_hits);
-        all_hits.append(&mut b_hits);
-        all_hits.sort_by(|h1, h2| h1.distance.partial_cmp(&h2.distance).unwrap());
-
-        remove_duplicate_hits(&mut all_hits);
-
-        let mut stack = 0;
-        let mut is_front_face = false;
+        // Inside the difference while inside the base and outside every subtracted child
+        let mut base_depth = 0;
+        let mut subtracted_depth = 0;
         let mut result = Vec::new();
-        for hit in all_hits {
-            let prev_stack = stack;
+        for (list, hit) in Merge::new(hit_lists) {
+            let is_base = list == base;
+            let was_inside = base_depth > 0 && subtracted_depth == 0;
+            let depth = if is_base {
+                &mut base_depth
+            } else {
+                &mut subtracted_depth
+            };
             if hit.is_front_face {
-                stack += 1;
+                *depth += 1;
             } else {
-                stack -= 1;
+                *depth -= 1;
             }
-            if prev_stack == 2 || stack == 2 {
-                is_front_face = !is_front_face;
-                result.push(if is_front_face == hit.is_front_face {
+            let is_inside = base_depth > 0 && subtracted_depth == 0;
+            if was_inside != is_inside {
+                let hit = if is_inside == hit.is_front_face {
                     hit
                 } else {
                     Hit {
-                        is_front_face,
+                        is_front_face: is_inside,
                         normal: -hit.normal,
                         ..hit
                     }
-                });
+                };
+                push_hit(&mut result, hit);
             }
         }
 
-        remove_duplicate_hits(&mut result);
-
         result
     }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.base.bounds
+    }
 }
@@ -5,13 +5,14 @@ use crate::{
 
 use super::{
     super::super::deserialize::{deserialize_ldr_color, deserialize_position, deserialize_scale},
+    sdf::{Sdf, SdfMaterial},
     Hit, RTModel,
 };
 
 use schemars::JsonSchema;
 use serde::Deserialize;
 use seui_engine_raytracing_csg_renderer_core::types::{
-    math::{Direction, Position, Vec3},
+    math::{Aabb, Direction, Position, Vec3},
     rt::Ray,
 };
 use seui_engine_raytracing_csg_renderer_types::LDRColor;
@@ -139,4 +140,37 @@ impl RTModel for Cube {
 
         result
     }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let extent = Vec3::new(self.scale.x, self.scale.y, self.scale.z);
+        Some(Aabb::from_points([
+            *self.position - extent,
+            *self.position + extent,
+        ]))
+    }
+}
+
+impl Sdf for Cube {
+    fn distance(&self, position: Vec3) -> f64 {
+        let p = position - *self.position;
+        let q = Vec3::new(
+            p.x.abs() - self.scale.x,
+            p.y.abs() - self.scale.y,
+            p.z.abs() - self.scale.z,
+        );
+        let outside = Vec3::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0));
+        outside.length() + q.x.max(q.y).max(q.z).min(0.0)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        RTModel::bounds(self)
+    }
+
+    fn material(&self, _position: Vec3) -> Option<SdfMaterial> {
+        Some(SdfMaterial {
+            albedo: self.albedo,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        })
+    }
 }
@@ -0,0 +1,69 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::math::{Direction, Position};
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+use crate::{
+    deserialize::{
+        deserialize_direction, deserialize_ldr_color, deserialize_ldr_float,
+        deserialize_nonnegative_float, deserialize_position,
+    },
+    json_schema::{DirectionSchema, LDRColorSchema, PositionSchema},
+};
+
+use super::{
+    cone::Frustum,
+    sdf::Sdf,
+    util::{one, up},
+    RTModel,
+};
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableCylinder {
+    /// Center of the cylinder, halfway between its caps
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    #[serde(default = "up", deserialize_with = "deserialize_direction")]
+    #[schemars(with = "DirectionSchema")]
+    axis: Direction,
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    height: f64,
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    radius: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_color")]
+    #[schemars(with = "LDRColorSchema")]
+    albedo: LDRColor,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: f64,
+}
+
+impl DeserializableCylinder {
+    pub fn into_rt_model(self) -> Box<dyn RTModel + Send + Sync> {
+        Box::new(self.into_frustum())
+    }
+
+    pub fn into_sdf(self) -> Box<dyn Sdf + Send + Sync> {
+        Box::new(self.into_frustum())
+    }
+
+    fn into_frustum(self) -> Frustum {
+        Frustum::new(
+            self.position,
+            self.axis,
+            self.height,
+            self.radius,
+            self.radius,
+            self.albedo,
+            self.roughness,
+            self.metallic,
+        )
+    }
+}
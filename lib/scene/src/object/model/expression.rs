@@ -0,0 +1,795 @@
+use std::collections::{BTreeMap, HashMap};
+
+use seui_engine_raytracing_csg_renderer_core::types::math::Vec3;
+
+/// Monomial exponents of x, y and z mapped to their coefficients.
+pub type Terms = BTreeMap<[u32; 3], f64>;
+
+/// Highest integer power expanded symbolically, larger ones are left to root finding.
+const MAX_EXPANDED_POWER: f64 = 64.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Exp,
+    Ln,
+    Sqrt,
+    Abs,
+    Min,
+    Max,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        Some(match name {
+            "sin" => Function::Sin,
+            "cos" => Function::Cos,
+            "tan" => Function::Tan,
+            "exp" => Function::Exp,
+            "ln" | "log" => Function::Ln,
+            "sqrt" => Function::Sqrt,
+            "abs" => Function::Abs,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            _ => return None,
+        })
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Function::Min | Function::Max => 2,
+            _ => 1,
+        }
+    }
+
+    fn apply(self, arguments: &[f64]) -> f64 {
+        match self {
+            Function::Sin => arguments[0].sin(),
+            Function::Cos => arguments[0].cos(),
+            Function::Tan => arguments[0].tan(),
+            Function::Exp => arguments[0].exp(),
+            Function::Ln => arguments[0].ln(),
+            Function::Sqrt => arguments[0].sqrt(),
+            Function::Abs => arguments[0].abs(),
+            Function::Min => arguments[0].min(arguments[1]),
+            Function::Max => arguments[0].max(arguments[1]),
+        }
+    }
+
+    fn apply_interval(self, arguments: &[Interval]) -> Interval {
+        let a = arguments[0];
+        match self {
+            Function::Sin => a.sin(),
+            Function::Cos => (a + Interval::point(std::f64::consts::FRAC_PI_2)).sin(),
+            Function::Tan => a.tan(),
+            Function::Exp => Interval::new(a.lo.exp(), a.hi.exp()),
+            Function::Ln => Interval::new(
+                if a.lo > 0.0 {
+                    a.lo.ln()
+                } else {
+                    f64::NEG_INFINITY
+                },
+                a.hi.ln(),
+            ),
+            Function::Sqrt => Interval::new(a.lo.max(0.0).sqrt(), a.hi.max(0.0).sqrt()),
+            Function::Abs => a.abs(),
+            Function::Min => Interval::new(a.lo.min(arguments[1].lo), a.hi.min(arguments[1].hi)),
+            Function::Max => Interval::new(a.lo.max(arguments[1].lo), a.hi.max(arguments[1].hi)),
+        }
+    }
+}
+
+/// Parsed scalar field of `x`, `y` and `z`, with parameters already substituted.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    Constant(f64),
+    /// 0 for x, 1 for y and 2 for z
+    Variable(usize),
+    Negate(Box<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Subtract(Box<Expression>, Box<Expression>),
+    Multiply(Box<Expression>, Box<Expression>),
+    Divide(Box<Expression>, Box<Expression>),
+    Power(Box<Expression>, Box<Expression>),
+    Call(Function, Vec<Expression>),
+}
+
+impl Expression {
+    pub fn evaluate(&self, p: Vec3) -> f64 {
+        match self {
+            Expression::Constant(c) => *c,
+            Expression::Variable(axis) => p[*axis],
+            Expression::Negate(a) => -a.evaluate(p),
+            Expression::Add(a, b) => a.evaluate(p) + b.evaluate(p),
+            Expression::Subtract(a, b) => a.evaluate(p) - b.evaluate(p),
+            Expression::Multiply(a, b) => a.evaluate(p) * b.evaluate(p),
+            Expression::Divide(a, b) => a.evaluate(p) / b.evaluate(p),
+            Expression::Power(a, b) => power(a.evaluate(p), b.evaluate(p)),
+            Expression::Call(function, arguments) => {
+                let values: Vec<f64> = arguments.iter().map(|a| a.evaluate(p)).collect();
+                function.apply(&values)
+            }
+        }
+    }
+
+    /// Range enclosing every value of the field over the box spanned by `p`.
+    pub fn evaluate_interval(&self, p: [Interval; 3]) -> Interval {
+        match self {
+            Expression::Constant(c) => Interval::point(*c),
+            Expression::Variable(axis) => p[*axis],
+            Expression::Negate(a) => -a.evaluate_interval(p),
+            Expression::Add(a, b) => a.evaluate_interval(p) + b.evaluate_interval(p),
+            Expression::Subtract(a, b) => a.evaluate_interval(p) - b.evaluate_interval(p),
+            Expression::Multiply(a, b) => a.evaluate_interval(p) * b.evaluate_interval(p),
+            Expression::Divide(a, b) => a.evaluate_interval(p) / b.evaluate_interval(p),
+            Expression::Power(a, b) => match **b {
+                Expression::Constant(exponent) => a.evaluate_interval(p).powf(exponent),
+                _ => Interval::ENTIRE,
+            },
+            Expression::Call(function, arguments) => {
+                let values: Vec<Interval> =
+                    arguments.iter().map(|a| a.evaluate_interval(p)).collect();
+                function.apply_interval(&values)
+            }
+        }
+    }
+
+    /// Expands the expression into monomials, if it is a polynomial.
+    pub fn to_terms(&self) -> Option<Terms> {
+        let mut terms = match self {
+            Expression::Constant(c) => Terms::from([([0, 0, 0], *c)]),
+            Expression::Variable(axis) => {
+                let mut exponents = [0, 0, 0];
+                exponents[*axis] = 1;
+                Terms::from([(exponents, 1.0)])
+            }
+            Expression::Negate(a) => scale(&a.to_terms()?, -1.0),
+            Expression::Add(a, b) => add(&a.to_terms()?, &b.to_terms()?, 1.0),
+            Expression::Subtract(a, b) => add(&a.to_terms()?, &b.to_terms()?, -1.0),
+            Expression::Multiply(a, b) => multiply(&a.to_terms()?, &b.to_terms()?),
+            Expression::Divide(a, b) => match **b {
+                Expression::Constant(divisor) if divisor != 0.0 => {
+                    scale(&a.to_terms()?, 1.0 / divisor)
+                }
+                _ => return None,
+            },
+            Expression::Power(a, b) => match **b {
+                Expression::Constant(exponent)
+                    if (0.0..=MAX_EXPANDED_POWER).contains(&exponent)
+                        && exponent.fract() == 0.0 =>
+                {
+                    let base = a.to_terms()?;
+                    let mut result = Terms::from([([0, 0, 0], 1.0)]);
+                    for _ in 0..exponent as u32 {
+                        result = multiply(&result, &base);
+                    }
+                    result
+                }
+                _ => return None,
+            },
+            Expression::Call(..) => return None,
+        };
+        terms.retain(|_, c| *c != 0.0);
+        Some(terms)
+    }
+}
+
+/// `powf`, except that integer exponents of negative bases stay defined.
+fn power(base: f64, exponent: f64) -> f64 {
+    if exponent.fract() == 0.0 && exponent.abs() <= i32::MAX as f64 {
+        base.powi(exponent as i32)
+    } else {
+        base.powf(exponent)
+    }
+}
+
+fn scale(a: &Terms, factor: f64) -> Terms {
+    a.iter().map(|(&e, &c)| (e, c * factor)).collect()
+}
+
+fn add(a: &Terms, b: &Terms, sign: f64) -> Terms {
+    let mut result = a.clone();
+    for (&e, &c) in b {
+        *result.entry(e).or_default() += sign * c;
+    }
+    result
+}
+
+fn multiply(a: &Terms, b: &Terms) -> Terms {
+    let mut result = Terms::new();
+    for (ea, ca) in a {
+        for (eb, cb) in b {
+            let e = [ea[0] + eb[0], ea[1] + eb[1], ea[2] + eb[2]];
+            *result.entry(e).or_default() += ca * cb;
+        }
+    }
+    result
+}
+
+/// Closed range of reals, used to prove that a field has no root over a box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interval {
+    pub lo: f64,
+    pub hi: f64,
+}
+
+impl Interval {
+    pub const ENTIRE: Interval = Interval {
+        lo: f64::NEG_INFINITY,
+        hi: f64::INFINITY,
+    };
+
+    pub fn new(lo: f64, hi: f64) -> Interval {
+        if lo.is_nan() || hi.is_nan() {
+            Interval::ENTIRE
+        } else {
+            Interval { lo, hi }
+        }
+    }
+
+    pub fn point(value: f64) -> Interval {
+        Interval::new(value, value)
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        self.lo <= value && value <= self.hi
+    }
+
+    fn abs(self) -> Interval {
+        if self.lo >= 0.0 {
+            self
+        } else if self.hi <= 0.0 {
+            -self
+        } else {
+            Interval::new(0.0, self.hi.max(-self.lo))
+        }
+    }
+
+    fn powf(self, exponent: f64) -> Interval {
+        if exponent.fract() != 0.0 || exponent.abs() > i32::MAX as f64 {
+            // Only defined for nonnegative bases, where it is monotonic
+            let lo = self.lo.max(0.0);
+            let (a, b) = (lo.powf(exponent), self.hi.powf(exponent));
+            return Interval::new(a.min(b), a.max(b));
+        }
+        let n = exponent as i32;
+        if n < 0 {
+            return Interval::point(1.0) / self.powf(-exponent);
+        }
+        let (a, b) = (self.lo.powi(n), self.hi.powi(n));
+        if n % 2 == 1 {
+            Interval::new(a, b)
+        } else if self.contains(0.0) {
+            Interval::new(0.0, a.max(b))
+        } else {
+            Interval::new(a.min(b), a.max(b))
+        }
+    }
+
+    fn sin(self) -> Interval {
+        use std::f64::consts::{FRAC_PI_2, TAU};
+
+        if self.hi - self.lo >= TAU {
+            return Interval::new(-1.0, 1.0);
+        }
+        let (a, b) = (self.lo.sin(), self.hi.sin());
+        let mut result = Interval::new(a.min(b), a.max(b));
+        // Whether a point of the form `offset + k 2π` lies in the range
+        let reaches = |offset: f64| ((self.lo - offset) / TAU).ceil() * TAU + offset <= self.hi;
+        if reaches(FRAC_PI_2) {
+            result.hi = 1.0;
+        }
+        if reaches(-FRAC_PI_2) {
+            result.lo = -1.0;
+        }
+        result
+    }
+
+    fn tan(self) -> Interval {
+        use std::f64::consts::{FRAC_PI_2, PI};
+
+        if self.hi - self.lo >= PI
+            || ((self.lo - FRAC_PI_2) / PI).ceil() * PI + FRAC_PI_2 <= self.hi
+        {
+            Interval::ENTIRE
+        } else {
+            Interval::new(self.lo.tan(), self.hi.tan())
+        }
+    }
+}
+
+impl std::ops::Neg for Interval {
+    type Output = Interval;
+
+    fn neg(self) -> Interval {
+        Interval::new(-self.hi, -self.lo)
+    }
+}
+
+impl std::ops::Add for Interval {
+    type Output = Interval;
+
+    fn add(self, rhs: Interval) -> Interval {
+        Interval::new(self.lo + rhs.lo, self.hi + rhs.hi)
+    }
+}
+
+impl std::ops::Sub for Interval {
+    type Output = Interval;
+
+    fn sub(self, rhs: Interval) -> Interval {
+        Interval::new(self.lo - rhs.hi, self.hi - rhs.lo)
+    }
+}
+
+impl std::ops::Mul for Interval {
+    type Output = Interval;
+
+    fn mul(self, rhs: Interval) -> Interval {
+        let products = [
+            self.lo * rhs.lo,
+            self.lo * rhs.hi,
+            self.hi * rhs.lo,
+            self.hi * rhs.hi,
+        ];
+        // 0 * inf is NaN, which `new` widens to the whole line
+        Interval::new(
+            products.iter().copied().fold(f64::INFINITY, f64::min),
+            products.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        )
+    }
+}
+
+impl std::ops::Div for Interval {
+    type Output = Interval;
+
+    fn div(self, rhs: Interval) -> Interval {
+        if rhs.contains(0.0) {
+            Interval::ENTIRE
+        } else {
+            self * Interval::new(1.0 / rhs.hi, 1.0 / rhs.lo)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Symbol(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let characters: Vec<char> = source.chars().collect();
+    let mut i = 0;
+    while i < characters.len() {
+        let c = characters[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < characters.len() && (characters[i].is_ascii_digit() || characters[i] == '.') {
+                i += 1;
+            }
+            // An exponent only when digits follow, `2e` is 2 times a parameter `e`
+            if i < characters.len() && (characters[i] == 'e' || characters[i] == 'E') {
+                let mut j = i + 1;
+                if j < characters.len() && (characters[j] == '+' || characters[j] == '-') {
+                    j += 1;
+                }
+                if j < characters.len() && characters[j].is_ascii_digit() {
+                    i = j;
+                    while i < characters.len() && characters[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = characters[start..i].iter().collect();
+            let value = text
+                .parse()
+                .map_err(|_| format!("Invalid number \"{}\"", text))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < characters.len() && (characters[i].is_alphanumeric() || characters[i] == '_')
+            {
+                i += 1;
+            }
+            tokens.push(Token::Name(characters[start..i].iter().collect()));
+        } else if "+-*/^(),=".contains(c) {
+            tokens.push(Token::Symbol(c));
+            i += 1;
+        } else {
+            return Err(format!("Unexpected character '{}'", c));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    parameters: &'a HashMap<String, f64>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(format!("Expected '{}'", symbol))
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expression, String> {
+        let mut result = self.product()?;
+        loop {
+            if self.eat('+') {
+                result = fold(Expression::Add(Box::new(result), Box::new(self.product()?)));
+            } else if self.eat('-') {
+                result = fold(Expression::Subtract(
+                    Box::new(result),
+                    Box::new(self.product()?),
+                ));
+            } else {
+                return Ok(result);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<Expression, String> {
+        let mut result = self.unary()?;
+        loop {
+            if self.eat('*') {
+                result = fold(Expression::Multiply(
+                    Box::new(result),
+                    Box::new(self.unary()?),
+                ));
+            } else if self.eat('/') {
+                result = fold(Expression::Divide(
+                    Box::new(result),
+                    Box::new(self.unary()?),
+                ));
+            } else if matches!(
+                self.peek(),
+                Some(Token::Number(_) | Token::Name(_) | Token::Symbol('('))
+            ) {
+                // Juxtaposition, as in `4R^2(x^2 + y^2)`
+                result = fold(Expression::Multiply(
+                    Box::new(result),
+                    Box::new(self.power()?),
+                ));
+            } else {
+                return Ok(result);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expression, String> {
+        if self.eat('-') {
+            Ok(fold(Expression::Negate(Box::new(self.unary()?))))
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<Expression, String> {
+        let base = self.primary()?;
+        if self.eat('^') {
+            // Right associative, and binding tighter than a leading minus: -x^2 is -(x^2)
+            Ok(fold(Expression::Power(
+                Box::new(base),
+                Box::new(self.unary()?),
+            )))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expression, String> {
+        match self.tokens.get(self.position).cloned() {
+            Some(Token::Number(value)) => {
+                self.position += 1;
+                Ok(Expression::Constant(value))
+            }
+            Some(Token::Symbol('(')) => {
+                self.position += 1;
+                let result = self.sum()?;
+                self.expect(')')?;
+                Ok(result)
+            }
+            Some(Token::Name(name)) => {
+                self.position += 1;
+                if let Some(function) = Function::from_name(&name) {
+                    if self.eat('(') {
+                        let mut arguments = vec![self.sum()?];
+                        while self.eat(',') {
+                            arguments.push(self.sum()?);
+                        }
+                        self.expect(')')?;
+                        if arguments.len() != function.arity() {
+                            return Err(format!(
+                                "{} takes {} argument(s), got {}",
+                                name,
+                                function.arity(),
+                                arguments.len()
+                            ));
+                        }
+                        return Ok(fold(Expression::Call(function, arguments)));
+                    }
+                }
+                self.name(&name)
+            }
+            Some(Token::Symbol(c)) => Err(format!("Unexpected '{}'", c)),
+            None => Err("Unexpected end of equation".to_string()),
+        }
+    }
+
+    /// A variable or parameter, or a run of variables like `xy`.
+    ///
+    /// Other runs are not split, so that a mistyped parameter like `Rr` is an error.
+    fn name(&self, name: &str) -> Result<Expression, String> {
+        if let Some(value) = self.single_name(name) {
+            return Ok(value);
+        }
+        if !name.chars().all(|c| matches!(c, 'x' | 'y' | 'z')) {
+            return Err(format!("Unknown name \"{}\"", name));
+        }
+        name.chars()
+            .filter_map(|c| self.single_name(&c.to_string()))
+            .reduce(|product, variable| {
+                fold(Expression::Multiply(Box::new(product), Box::new(variable)))
+            })
+            .ok_or_else(|| format!("Unknown name \"{}\"", name))
+    }
+
+    fn single_name(&self, name: &str) -> Option<Expression> {
+        if let Some(&value) = self.parameters.get(name) {
+            return Some(Expression::Constant(value));
+        }
+        match name {
+            "x" => Some(Expression::Variable(0)),
+            "y" => Some(Expression::Variable(1)),
+            "z" => Some(Expression::Variable(2)),
+            "pi" => Some(Expression::Constant(std::f64::consts::PI)),
+            _ => None,
+        }
+    }
+}
+
+/// Replaces operations on constants by their value.
+fn fold(expression: Expression) -> Expression {
+    let is_constant = match &expression {
+        Expression::Negate(a) => matches!(**a, Expression::Constant(_)),
+        Expression::Add(a, b)
+        | Expression::Subtract(a, b)
+        | Expression::Multiply(a, b)
+        | Expression::Divide(a, b)
+        | Expression::Power(a, b) => {
+            matches!(**a, Expression::Constant(_)) && matches!(**b, Expression::Constant(_))
+        }
+        Expression::Call(_, arguments) => arguments
+            .iter()
+            .all(|a| matches!(a, Expression::Constant(_))),
+        Expression::Constant(_) | Expression::Variable(_) => false,
+    };
+    if is_constant {
+        Expression::Constant(expression.evaluate(Vec3::new(0.0, 0.0, 0.0)))
+    } else {
+        expression
+    }
+}
+
+/// Parses `f(x, y, z)` or `lhs = rhs`, which becomes `lhs - rhs`.
+///
+/// Supports `+ - * / ^`, juxtaposition as multiplication, `pi` and the functions
+/// `sin cos tan exp ln sqrt abs min max`. Names of `parameters` are replaced by their values.
+pub fn parse(source: &str, parameters: &HashMap<String, f64>) -> Result<Expression, String> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        position: 0,
+        parameters,
+    };
+    let mut result = parser.sum()?;
+    if parser.eat('=') {
+        result = fold(Expression::Subtract(
+            Box::new(result),
+            Box::new(parser.sum()?),
+        ));
+    }
+    match parser.peek() {
+        None => Ok(result),
+        Some(token) => Err(format!("Unexpected {:?}", token)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use seui_engine_raytracing_csg_renderer_core::types::math::Vec3;
+
+    use super::{parse, Interval, Terms};
+
+    fn parameters(pairs: &[(&str, f64)]) -> HashMap<String, f64> {
+        pairs
+            .iter()
+            .map(|&(name, value)| (name.to_string(), value))
+            .collect()
+    }
+
+    fn value(source: &str, pairs: &[(&str, f64)], p: [f64; 3]) -> f64 {
+        parse(source, &parameters(pairs))
+            .unwrap()
+            .evaluate(Vec3::new(p[0], p[1], p[2]))
+    }
+
+    fn assert_terms(found: &Terms, expected: &[([u32; 3], f64)]) {
+        assert_eq!(found.len(), expected.len(), "{found:?}");
+        for (exponents, coefficient) in expected {
+            let found = found[exponents];
+            assert!(
+                (found - coefficient).abs() < 1e-12,
+                "{exponents:?}: {found}"
+            );
+        }
+    }
+
+    /// Deterministic values in `[0, 1)`, so that failures can be reproduced.
+    fn sampler(mut state: u64) -> impl FnMut() -> f64 {
+        move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        }
+    }
+
+    #[test]
+    fn expands_torus() {
+        let torus = parse(
+            "(x^2+y^2+z^2+R^2-r^2)^2 - 4R^2(x^2+y^2)",
+            &parameters(&[("R", 2.0), ("r", 0.5)]),
+        )
+        .unwrap();
+        // (s + k)^2 - 4 R^2 (x^2 + y^2) with s = x^2 + y^2 + z^2 and k = R^2 - r^2
+        let k: f64 = 2.0 * 2.0 - 0.5 * 0.5;
+        assert_terms(
+            &torus.to_terms().unwrap(),
+            &[
+                ([4, 0, 0], 1.0),
+                ([0, 4, 0], 1.0),
+                ([0, 0, 4], 1.0),
+                ([2, 2, 0], 2.0),
+                ([2, 0, 2], 2.0),
+                ([0, 2, 2], 2.0),
+                ([2, 0, 0], 2.0 * k - 16.0),
+                ([0, 2, 0], 2.0 * k - 16.0),
+                ([0, 0, 2], 2.0 * k),
+                ([0, 0, 0], k * k),
+            ],
+        );
+    }
+
+    #[test]
+    fn expands_integer_powers_only() {
+        let none = HashMap::new();
+        assert_terms(
+            &parse("(x+1)^3", &none).unwrap().to_terms().unwrap(),
+            &[
+                ([3, 0, 0], 1.0),
+                ([2, 0, 0], 3.0),
+                ([1, 0, 0], 3.0),
+                ([0, 0, 0], 1.0),
+            ],
+        );
+        assert_terms(
+            &parse("x^2 = x^2 + y", &none).unwrap().to_terms().unwrap(),
+            &[([0, 1, 0], -1.0)],
+        );
+        assert_eq!(parse("x^0.5", &none).unwrap().to_terms(), None);
+        assert_eq!(parse("x^-1", &none).unwrap().to_terms(), None);
+        assert_eq!(parse("x^y", &none).unwrap().to_terms(), None);
+        assert_eq!(parse("x/y", &none).unwrap().to_terms(), None);
+        assert_eq!(parse("sin(x)", &none).unwrap().to_terms(), None);
+    }
+
+    #[test]
+    fn follows_precedence() {
+        let p = [3.0, 2.0, 5.0];
+        assert_eq!(value("-x^2", &[], p), -9.0);
+        assert_eq!(value("2^3^2", &[], p), 512.0);
+        assert_eq!(value("2^-1", &[], p), 0.5);
+        assert_eq!(value("x - y - z", &[], p), -4.0);
+        assert_eq!(value("z / y * x", &[], p), 7.5);
+        // Juxtaposition binds like a power's operands, tighter than a leading minus
+        assert_eq!(value("2x^2", &[], p), 18.0);
+        assert_eq!(value("-2x", &[], p), -6.0);
+        assert_eq!(value("xy", &[], p), 6.0);
+        assert_eq!(value("x(y + 1)", &[], p), 9.0);
+        assert_eq!(value("x = y + 1", &[], p), 0.0);
+        assert_eq!(value("min(x, y) + max(x, y)", &[], p), 5.0);
+    }
+
+    #[test]
+    fn reads_exponents_only_before_digits() {
+        let e = [("e", std::f64::consts::E)];
+        let p = [3.0, 2.0, 5.0];
+        assert_eq!(value("2e3", &e, p), 2000.0);
+        assert_eq!(value("2E-1", &e, p), 0.2);
+        assert_eq!(value("2e+1", &e, p), 20.0);
+        assert_eq!(value("2e", &e, p), 2.0 * std::f64::consts::E);
+        assert_eq!(value("2e+x", &e, p), 2.0 * std::f64::consts::E + 3.0);
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        let none = HashMap::new();
+        let r = parameters(&[("R", 2.0), ("r", 0.5)]);
+        assert!(parse("Rr", &r).is_err());
+        assert!(parse("ab", &none).is_err());
+        assert!(parse("xw", &none).is_err());
+        assert!(parse("sin", &none).is_err());
+        assert!(parse("min(x)", &none).is_err());
+        assert!(parse("(x", &none).is_err());
+        assert!(parse("x y )", &none).is_err());
+        assert!(parse("x # y", &none).is_err());
+    }
+
+    #[test]
+    fn intervals_enclose_values() {
+        let none = HashMap::new();
+        let mut random = sampler(0x5eed);
+        for source in [
+            "x^2 + y^2 + z^2 - 1",
+            "(x^2+y^2+z^2+3)^2 - 16(x^2+y^2)",
+            "-x^3 + x y z",
+            "sin(x y) + cos(z) - tan(x / 4)",
+            "exp(x) / (y + 5) - ln(z + 5)",
+            "sqrt(abs(x)) - min(y, z) * max(x, z)",
+            "x^-2 + y^1.5",
+        ] {
+            let expression = parse(source, &none).unwrap();
+            for _ in 0..200 {
+                let bounds: [Interval; 3] = std::array::from_fn(|_| {
+                    let (a, b) = (8.0 * random() - 4.0, 8.0 * random() - 4.0);
+                    Interval::new(a.min(b), a.max(b))
+                });
+                let range = expression.evaluate_interval(bounds);
+                for _ in 0..20 {
+                    let p: [f64; 3] = std::array::from_fn(|i| {
+                        bounds[i].lo + (bounds[i].hi - bounds[i].lo) * random()
+                    });
+                    let value = expression.evaluate(Vec3::new(p[0], p[1], p[2]));
+                    if value.is_nan() {
+                        continue;
+                    }
+                    // Allow for rounding of the interval's own bounds
+                    let slack = 1e-9 * value.abs().max(1.0);
+                    assert!(
+                        range.lo - slack <= value && value <= range.hi + slack,
+                        "{source} at {p:?} gives {value} outside {range:?}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Direction, Position, Vec3},
+    rt::Ray,
+};
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+use crate::{
+    deserialize::{
+        deserialize_direction, deserialize_ldr_color, deserialize_ldr_float,
+        deserialize_nonnegative_float, deserialize_position,
+    },
+    json_schema::{DirectionSchema, LDRColorSchema, PositionSchema},
+};
+
+use super::{
+    util::{disk_bounds, enhance_normal, one, orthonormal_basis, up},
+    Hit, RTModel,
+};
+
+/// Straight pieces each span of a spline profile is flattened into.
+const SPLINE_SEGMENTS: usize = 16;
+/// Halvings of the ray between the caps when looking for the sides of a twisted extrusion.
+const MAX_DEPTH: u32 = 50;
+
+/// Solid swept by a closed 2D profile moving along an axis, optionally turning and scaling.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableExtrude {
+    /// Center of the extrusion, halfway between its caps
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    #[serde(default = "up", deserialize_with = "deserialize_direction")]
+    #[schemars(with = "DirectionSchema")]
+    axis: Direction,
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    height: f64,
+    /// Closed polygon of `[x, y]` points in the plane of the bottom cap
+    profile: Vec<[f64; 2]>,
+    /// Pass a smooth closed Catmull-Rom spline through the points instead of straight edges
+    #[serde(default)]
+    spline: bool,
+    /// Degrees the profile turns around the axis from the bottom cap to the top one
+    #[serde(default)]
+    twist: f64,
+    /// Scale of the profile at the top cap, relative to the bottom one
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    taper: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_color")]
+    #[schemars(with = "LDRColorSchema")]
+    albedo: LDRColor,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: f64,
+}
+
+impl DeserializableExtrude {
+    pub fn into_rt_model(self) -> Box<dyn RTModel + Send + Sync> {
+        if self.profile.len() < 3 {
+            panic!("An extrusion profile needs at least 3 points");
+        }
+
+        let profile = if self.spline {
+            catmull_rom(&self.profile)
+        } else {
+            self.profile
+        };
+        let (u, v) = orthonormal_basis(self.axis);
+        Box::new(Extrude {
+            bottom: self.position + self.axis * (-self.height / 2.0),
+            u,
+            v,
+            axis: self.axis,
+            height: self.height,
+            profile,
+            twist: self.twist.to_radians(),
+            taper: self.taper,
+            albedo: self.albedo,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        })
+    }
+}
+
+/// Closed uniform Catmull-Rom spline through the points, flattened into a polygon.
+fn catmull_rom(points: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let n = points.len();
+    let mut polygon = Vec::with_capacity(n * SPLINE_SEGMENTS);
+    for i in 0..n {
+        let [p0, p1, p2, p3] = [n - 1, 0, 1, 2].map(|offset| points[(i + offset) % n]);
+        for step in 0..SPLINE_SEGMENTS {
+            let t = step as f64 / SPLINE_SEGMENTS as f64;
+            polygon.push(std::array::from_fn(|axis| {
+                0.5 * (2.0 * p1[axis]
+                    + (p2[axis] - p0[axis]) * t
+                    + (2.0 * p0[axis] - 5.0 * p1[axis] + 4.0 * p2[axis] - p3[axis]) * t * t
+                    + (3.0 * (p1[axis] - p2[axis]) + p3[axis] - p0[axis]) * t * t * t)
+            }));
+        }
+    }
+    polygon
+}
+
+fn cross(a: [f64; 2], b: [f64; 2]) -> f64 {
+    a[0] * b[1] - a[1] * b[0]
+}
+
+struct Extrude {
+    bottom: Position,
+    u: Vec3,
+    v: Vec3,
+    axis: Direction,
+    height: f64,
+    profile: Vec<[f64; 2]>,
+    /// Radians
+    twist: f64,
+    taper: f64,
+    albedo: LDRColor,
+    roughness: f64,
+    metallic: f64,
+}
+
+impl Extrude {
+    fn hit(&self, distance: f64, normal: Direction, is_front_face: bool) -> Hit {
+        Hit {
+            distance,
+            normal,
+            albedo: self.albedo,
+            is_front_face,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        }
+    }
+
+    fn angle(&self, z: f64) -> f64 {
+        self.twist * z / self.height
+    }
+
+    fn scale(&self, z: f64) -> f64 {
+        1.0 + (self.taper - 1.0) * z / self.height
+    }
+
+    /// Point of the local frame turned back by the twist at its height, the profile still scaled.
+    fn untwist(&self, point: Vec3) -> [f64; 2] {
+        let (sin, cos) = self.angle(point.z).sin_cos();
+        [cos * point.x + sin * point.y, cos * point.y - sin * point.x]
+    }
+
+    /// Zero on the surface swept by edge `i`, its sign telling the sides of the edge apart.
+    fn edge_field(&self, i: usize, point: Vec3) -> f64 {
+        let start = self.profile[i];
+        let end = self.profile[(i + 1) % self.profile.len()];
+        let edge = [end[0] - start[0], end[1] - start[1]];
+        let flat = self.untwist(point);
+        let scale = self.scale(point.z);
+        cross(
+            edge,
+            [flat[0] - start[0] * scale, flat[1] - start[1] * scale],
+        )
+    }
+
+    /// Gradient of [`Extrude::edge_field`], the normal of the side surface.
+    fn edge_normal(&self, i: usize, point: Vec3) -> Vec3 {
+        let start = self.profile[i];
+        let end = self.profile[(i + 1) % self.profile.len()];
+        // The field is n · R(-angle) (x, y) - (n · start) scale with n the edge turned by 90°
+        let n = [start[1] - end[1], end[0] - start[0]];
+        let (sin, cos) = self.angle(point.z).sin_cos();
+        let turned = [cos * n[0] - sin * n[1], sin * n[0] + cos * n[1]];
+        let twist_rate = self.twist / self.height;
+        let derivative = [
+            -sin * point.x + cos * point.y,
+            -cos * point.x - sin * point.y,
+        ];
+        let dz = twist_rate * (n[0] * derivative[0] + n[1] * derivative[1])
+            - (n[0] * start[0] + n[1] * start[1]) * (self.taper - 1.0) / self.height;
+        Vec3::new(turned[0], turned[1], dz)
+    }
+
+    /// Whether the crossing of edge `i`'s surface at `point` lies on the edge itself.
+    fn on_edge(&self, i: usize, point: Vec3) -> bool {
+        let scale = self.scale(point.z);
+        if scale <= 0.0 || !(0.0..=self.height).contains(&point.z) {
+            return false;
+        }
+        let start = self.profile[i];
+        let end = self.profile[(i + 1) % self.profile.len()];
+        let edge = [end[0] - start[0], end[1] - start[1]];
+        let flat = self.untwist(point);
+        let offset = [flat[0] / scale - start[0], flat[1] / scale - start[1]];
+        let s = (edge[0] * offset[0] + edge[1] * offset[1]) / (edge[0].powi(2) + edge[1].powi(2));
+        (0.0..1.0).contains(&s)
+    }
+
+    /// Even-odd test of a point of the profile's plane.
+    fn contains(&self, point: [f64; 2]) -> bool {
+        let mut inside = false;
+        for (i, &a) in self.profile.iter().enumerate() {
+            let b = self.profile[(i + 1) % self.profile.len()];
+            if (a[1] > point[1]) != (b[1] > point[1])
+                && point[0] < a[0] + (point[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0])
+            {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    /// Bound on how fast edge `i`'s field changes along the ray between `t0` and `t1`.
+    fn edge_slope(&self, i: usize, origin: Vec3, direction: Vec3, t0: f64, t1: f64) -> f64 {
+        let start = self.profile[i];
+        let end = self.profile[(i + 1) % self.profile.len()];
+        let edge = [end[0] - start[0], end[1] - start[1]];
+        // The field is edge × (R(-angle) (x, y) - start scale), the turn moves the
+        // point by the angle's rate times its distance from the axis, farthest at an end
+        let distance = |t: f64| (origin.x + direction.x * t).hypot(origin.y + direction.y * t);
+        let twist_rate = (self.twist * direction.z / self.height).abs();
+        let taper_rate = ((self.taper - 1.0) * direction.z / self.height).abs();
+        edge[0].hypot(edge[1])
+            * (twist_rate * distance(t0).max(distance(t1)) + direction.x.hypot(direction.y))
+            + cross(edge, start).abs() * taper_rate
+    }
+
+    /// Roots of edge `i`'s field between `t0` and `t1`, given with their values there.
+    ///
+    /// Pieces are split until the slope bound proves that the field keeps its sign over them
+    /// or they are small enough that a sign change is a single crossing, so a ray grazing a
+    /// twisted edge can't hide a pair of crossings between two samples.
+    #[allow(clippy::too_many_arguments)]
+    fn subdivide(
+        &self,
+        i: usize,
+        origin: Vec3,
+        direction: Vec3,
+        (t0, f0): (f64, f64),
+        (t1, f1): (f64, f64),
+        depth: u32,
+        roots: &mut Vec<f64>,
+    ) {
+        let changes_sign = (f0 < 0.0) != (f1 < 0.0);
+        let slope = self.edge_slope(i, origin, direction, t0, t1);
+        if !changes_sign && f0.abs() + f1.abs() > slope * (t1 - t0) {
+            return;
+        }
+        if depth == MAX_DEPTH {
+            if changes_sign {
+                roots.push(0.5 * (t0 + t1));
+            }
+            return;
+        }
+        let middle = 0.5 * (t0 + t1);
+        let middle = (middle, self.edge_field(i, origin + direction * middle));
+        self.subdivide(i, origin, direction, (t0, f0), middle, depth + 1, roots);
+        self.subdivide(i, origin, direction, middle, (t1, f1), depth + 1, roots);
+    }
+
+    /// Every crossing ahead of the ray as (distance, normal in the local frame).
+    fn crossings(&self, origin: Vec3, direction: Vec3) -> Vec<(f64, Vec3)> {
+        let mut crossings = Vec::new();
+
+        // Caps
+        if direction.z != 0.0 {
+            for (z, normal) in [(0.0, -Vec3::Z), (self.height, Vec3::Z)] {
+                let t = (z - origin.z) / direction.z;
+                let scale = self.scale(z);
+                if t > 0.0 && scale > 0.0 {
+                    let flat = self.untwist(origin + direction * t);
+                    if self.contains([flat[0] / scale, flat[1] / scale]) {
+                        crossings.push((t, normal));
+                    }
+                }
+            }
+        }
+
+        // Part of the ray between the caps
+        let (lo, hi) = if direction.z == 0.0 {
+            if !(0.0..=self.height).contains(&origin.z) {
+                return crossings;
+            }
+            (0.0, f64::INFINITY)
+        } else {
+            let (t0, t1) = (
+                -origin.z / direction.z,
+                (self.height - origin.z) / direction.z,
+            );
+            (t0.min(t1).max(0.0), t0.max(t1))
+        };
+        if lo >= hi {
+            return crossings;
+        }
+
+        // Sides
+        let point = |t: f64| origin + direction * t;
+        let mut push = |i: usize, t: f64| {
+            if t > 0.0 && self.on_edge(i, point(t)) {
+                crossings.push((t, self.edge_normal(i, point(t))));
+            }
+        };
+        if self.twist == 0.0 || direction.z == 0.0 {
+            // Without turning along the ray every edge field is linear in the distance
+            for i in 0..self.profile.len() {
+                let f0 = self.edge_field(i, origin);
+                let f1 = self.edge_field(i, origin + direction) - f0;
+                if f1 != 0.0 {
+                    push(i, -f0 / f1);
+                }
+            }
+        } else {
+            for i in 0..self.profile.len() {
+                let mut roots = Vec::new();
+                let field = |t: f64| self.edge_field(i, point(t));
+                self.subdivide(
+                    i,
+                    origin,
+                    direction,
+                    (lo, field(lo)),
+                    (hi, field(hi)),
+                    0,
+                    &mut roots,
+                );
+                for t in roots {
+                    push(i, t);
+                }
+            }
+        }
+
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+        crossings
+    }
+}
+
+impl RTModel for Extrude {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let mut result = Vec::new();
+        if self.height <= 0.0 {
+            return result;
+        }
+
+        // Work in the local frame where the bottom cap is at z = 0 and the top cap at z = height
+        let origin = ray.origin - self.bottom;
+        let origin = Vec3::new(
+            origin.dot(self.u),
+            origin.dot(self.v),
+            origin.dot(*self.axis),
+        );
+        let direction: Vec3 = *ray.direction;
+        let direction = Vec3::new(
+            direction.dot(self.u),
+            direction.dot(self.v),
+            direction.dot(*self.axis),
+        );
+        let to_world = |n: Vec3| Direction::new(self.u * n.x + self.v * n.y + *self.axis * n.z);
+
+        let crossings = self.crossings(origin, direction);
+
+        // Parity of the crossings ahead tells whether the ray starts inside
+        let mut is_front_face = crossings.len() % 2 == 1;
+        if is_front_face {
+            result.push(self.hit(0.0, -ray.direction, true));
+        }
+        for (distance, normal) in crossings {
+            is_front_face = !is_front_face;
+            result.push(self.hit(
+                distance,
+                enhance_normal(ray.direction, to_world(normal), is_front_face),
+                is_front_face,
+            ));
+        }
+
+        result
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        // The twist turns the profile, so bound it by the circle around its farthest point
+        let radius = self
+            .profile
+            .iter()
+            .map(|p| p[0].hypot(p[1]))
+            .fold(0.0, f64::max);
+        let bottom = *self.bottom;
+        let top = bottom + *self.axis * self.height;
+        Some(disk_bounds(bottom, self.axis, radius).union(disk_bounds(
+            top,
+            self.axis,
+            radius * self.taper,
+        )))
+    }
+}
@@ -0,0 +1,309 @@
+//! Bounded flat shapes, seen from both sides.
+//!
+//! They have no thickness, a ray leaves them right where it enters. In CSG they act as sheets: a
+//! `union` keeps them whole, an `intersection` with a solid keeps the part of the sheet inside it,
+//! subtracting a solid from them cuts holes, and subtracting them from a solid removes nothing.
+
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Direction, Move, Position, Vec3},
+    rt::Ray,
+};
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+use crate::{
+    deserialize::{
+        deserialize_direction, deserialize_ldr_color, deserialize_ldr_float, deserialize_move,
+        deserialize_nonnegative_float, deserialize_position,
+    },
+    json_schema::{DirectionSchema, LDRColorSchema, MoveSchema, PositionSchema},
+    texture::{DeserializableTexture, Texture},
+    BuildContext, ImageLoader, MeshLoader,
+};
+
+use super::{
+    util::{enhance_normal, one, orthonormal_basis, up},
+    Hit, RTModel,
+};
+
+/// Distance between entering and leaving a sheet, just above the one below which CSG drops a
+/// piece as a coincident face, so that sheets survive it.
+const THICKNESS: f64 = 1e-5;
+
+fn x_edge() -> Move {
+    Move::new(Vec3::X)
+}
+
+fn y_edge() -> Move {
+    Move::new(Vec3::Y)
+}
+
+/// Round sheet, with `u` and `v` running across it from 0 to 1.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableDisk {
+    /// Center of the disk
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    #[serde(default = "up", deserialize_with = "deserialize_direction")]
+    #[schemars(with = "DirectionSchema")]
+    normal: Direction,
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    radius: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_color")]
+    #[schemars(with = "LDRColorSchema")]
+    albedo: LDRColor,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: f64,
+    #[serde(default)]
+    texture: Option<DeserializableTexture>,
+}
+
+/// Parallelogram sheet spanned by two edges, a rectangle when they are perpendicular.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableRectangle {
+    /// Center of the rectangle
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    /// Whole edge along which the texture coordinate `u` goes from 0 to 1
+    #[serde(default = "x_edge", deserialize_with = "deserialize_move")]
+    #[schemars(with = "MoveSchema")]
+    u: Move,
+    /// Whole edge along which the texture coordinate `v` goes from 0 to 1
+    #[serde(default = "y_edge", deserialize_with = "deserialize_move")]
+    #[schemars(with = "MoveSchema")]
+    v: Move,
+    #[serde(default, deserialize_with = "deserialize_ldr_color")]
+    #[schemars(with = "LDRColorSchema")]
+    albedo: LDRColor,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: f64,
+    #[serde(default)]
+    texture: Option<DeserializableTexture>,
+}
+
+/// Triangular sheet, with `u` growing from `a` to `b` and `v` from `a` to `c`.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableTriangle {
+    #[serde(deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    a: Position,
+    #[serde(deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    b: Position,
+    #[serde(deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    c: Position,
+    #[serde(default, deserialize_with = "deserialize_ldr_color")]
+    #[schemars(with = "LDRColorSchema")]
+    albedo: LDRColor,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: f64,
+    #[serde(default)]
+    texture: Option<DeserializableTexture>,
+}
+
+impl DeserializableDisk {
+    pub fn into_rt_model<T: ImageLoader, M: MeshLoader>(
+        self,
+        context: &mut BuildContext<T, M>,
+    ) -> Box<dyn RTModel + Send + Sync> {
+        let (u, v) = orthonormal_basis(self.normal);
+        Box::new(Flat::new(
+            Shape::Disk,
+            *self.position,
+            [u * self.radius, v * self.radius],
+            self.albedo,
+            self.roughness,
+            self.metallic,
+            self.texture
+                .map(|t| t.into_texture(&mut context.image_cache)),
+        ))
+    }
+}
+
+impl DeserializableRectangle {
+    pub fn into_rt_model<T: ImageLoader, M: MeshLoader>(
+        self,
+        context: &mut BuildContext<T, M>,
+    ) -> Box<dyn RTModel + Send + Sync> {
+        Box::new(Flat::new(
+            Shape::Rectangle,
+            *self.position,
+            [*self.u * 0.5, *self.v * 0.5],
+            self.albedo,
+            self.roughness,
+            self.metallic,
+            self.texture
+                .map(|t| t.into_texture(&mut context.image_cache)),
+        ))
+    }
+}
+
+impl DeserializableTriangle {
+    pub fn into_rt_model<T: ImageLoader, M: MeshLoader>(
+        self,
+        context: &mut BuildContext<T, M>,
+    ) -> Box<dyn RTModel + Send + Sync> {
+        Box::new(Flat::new(
+            Shape::Triangle,
+            *self.a,
+            [self.b - self.a, self.c - self.a].map(|edge| *edge),
+            self.albedo,
+            self.roughness,
+            self.metallic,
+            self.texture
+                .map(|t| t.into_texture(&mut context.image_cache)),
+        ))
+    }
+}
+
+enum Shape {
+    /// Unit circle around the origin
+    Disk,
+    /// Square from -1 to 1 on both axes
+    Rectangle,
+    /// Corners at the origin and at the ends of both axes
+    Triangle,
+}
+
+/// Part of a plane, in coordinates `(s, t)` meaning `origin + s * axes[0] + t * axes[1]`.
+struct Flat {
+    shape: Shape,
+    origin: Vec3,
+    axes: [Vec3; 2],
+    /// Dual basis, giving back `s` and `t` by dot products
+    duals: [Vec3; 2],
+    normal: Vec3,
+    albedo: LDRColor,
+    roughness: f64,
+    metallic: f64,
+    texture: Option<Arc<dyn Texture + Send + Sync>>,
+}
+
+impl Flat {
+    fn new(
+        shape: Shape,
+        origin: Vec3,
+        axes: [Vec3; 2],
+        albedo: LDRColor,
+        roughness: f64,
+        metallic: f64,
+        texture: Option<Arc<dyn Texture + Send + Sync>>,
+    ) -> Flat {
+        let normal = axes[0].cross(axes[1]);
+        let area = normal.dot(normal);
+        Flat {
+            shape,
+            origin,
+            axes,
+            duals: [
+                axes[1].cross(normal) * (1.0 / area),
+                normal.cross(axes[0]) * (1.0 / area),
+            ],
+            normal,
+            albedo,
+            roughness,
+            metallic,
+            texture,
+        }
+    }
+
+    /// Texture coordinates of the point `(s, t)`, if it lies on the shape.
+    fn uv(&self, s: f64, t: f64) -> Option<(f64, f64)> {
+        match self.shape {
+            Shape::Disk if s * s + t * t <= 1.0 => Some((0.5 * (s + 1.0), 0.5 * (t + 1.0))),
+            Shape::Rectangle if s.abs() <= 1.0 && t.abs() <= 1.0 => {
+                Some((0.5 * (s + 1.0), 0.5 * (t + 1.0)))
+            }
+            Shape::Triangle if s >= 0.0 && t >= 0.0 && s + t <= 1.0 => Some((s, t)),
+            _ => None,
+        }
+    }
+
+    fn hit(&self, distance: f64, normal: Direction, albedo: LDRColor, is_front_face: bool) -> Hit {
+        Hit {
+            distance,
+            normal,
+            albedo,
+            is_front_face,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        }
+    }
+}
+
+impl RTModel for Flat {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let denominator = self.normal.dot(*ray.direction);
+        // Degenerate shapes have no normal and rays along the plane never cross it
+        if denominator == 0.0 || !denominator.is_finite() {
+            return Vec::new();
+        }
+        let distance = (self.origin - *ray.origin).dot(self.normal) / denominator;
+        if distance <= 0.0 {
+            return Vec::new();
+        }
+
+        let offset = *ray.origin + *ray.direction * distance - self.origin;
+        let Some((u, v)) = self.uv(offset.dot(self.duals[0]), offset.dot(self.duals[1])) else {
+            return Vec::new();
+        };
+        let albedo = match &self.texture {
+            // Texture coordinates grow upwards, image rows downwards
+            Some(texture) => texture.get(u, 1.0 - v),
+            None => self.albedo,
+        };
+
+        let normal = Direction::new(self.normal);
+        vec![
+            self.hit(
+                distance,
+                enhance_normal(ray.direction, normal, true),
+                albedo,
+                true,
+            ),
+            self.hit(
+                distance + THICKNESS,
+                enhance_normal(ray.direction, normal, false),
+                albedo,
+                false,
+            ),
+        ]
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let [a, b] = self.axes;
+        Some(match self.shape {
+            Shape::Disk => {
+                // The circle spanned by two perpendicular axes of equal length
+                let extent = Vec3::new(a.x.hypot(b.x), a.y.hypot(b.y), a.z.hypot(b.z));
+                Aabb::new(self.origin - extent, self.origin + extent)
+            }
+            Shape::Rectangle => {
+                Aabb::from_points([a + b, a - b, b - a, -a - b].map(|corner| self.origin + corner))
+            }
+            Shape::Triangle => Aabb::from_points([self.origin, self.origin + a, self.origin + b]),
+        })
+    }
+}
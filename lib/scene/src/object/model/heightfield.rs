@@ -0,0 +1,458 @@
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Direction, Position, Vec3},
+    rt::Ray,
+};
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+use crate::{
+    deserialize::{
+        deserialize_ldr_color, deserialize_ldr_float, deserialize_position,
+        deserialize_positive_float,
+    },
+    json_schema::{float_positive, LDRColorSchema, PositionSchema},
+    BuildContext, Image, ImageLoader, MeshLoader,
+};
+
+use super::{
+    util::{enhance_normal, one, zero},
+    Hit, RTModel,
+};
+
+fn default_smooth() -> bool {
+    true
+}
+
+/// Terrain centered on `position` in the XY plane, with the image's top row towards +Y.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableHeightfield {
+    /// Grayscale image, relative to the scene file
+    path: String,
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    /// Extent along X
+    #[serde(default = "one", deserialize_with = "deserialize_positive_float")]
+    #[schemars(schema_with = "float_positive")]
+    width: f64,
+    /// Extent along Y
+    #[serde(default = "one", deserialize_with = "deserialize_positive_float")]
+    #[schemars(schema_with = "float_positive")]
+    depth: f64,
+    /// Height of black pixels
+    #[serde(default = "zero")]
+    min_height: f64,
+    /// Height of white pixels
+    #[serde(default = "one")]
+    max_height: f64,
+    /// Interpolate normals between the image's pixels instead of using flat triangles
+    #[serde(default = "default_smooth")]
+    smooth: bool,
+    /// Add side walls and a bottom at `minHeight`, so the terrain is a solid for CSG
+    #[serde(default)]
+    closed: bool,
+    #[serde(default, deserialize_with = "deserialize_ldr_color")]
+    #[schemars(with = "LDRColorSchema")]
+    albedo: LDRColor,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: f64,
+}
+
+impl DeserializableHeightfield {
+    pub fn into_rt_model<T: ImageLoader, M: MeshLoader>(
+        self,
+        context: &mut BuildContext<T, M>,
+    ) -> Box<dyn RTModel + Send + Sync> {
+        let image = context.image_cache.load(&self.path);
+        if image.width() < 2 || image.height() < 2 {
+            panic!(
+                "Heightfield image \"{}\" needs at least 2x2 pixels",
+                self.path
+            );
+        }
+        Box::new(Heightfield::new(image, self))
+    }
+}
+
+/// Height ranges of blocks of cells, each level halving the previous one.
+struct Level {
+    columns: usize,
+    rows: usize,
+    ranges: Vec<(f64, f64)>,
+}
+
+struct Heightfield {
+    position: Position,
+    columns: usize,
+    rows: usize,
+    cell_width: f64,
+    cell_depth: f64,
+    /// Vertex heights, row by row
+    heights: Vec<f64>,
+    normals: Vec<Vec3>,
+    /// Level 0 covers single cells, the last level the whole field
+    pyramid: Vec<Level>,
+    bounds: Aabb,
+    smooth: bool,
+    closed: bool,
+    albedo: LDRColor,
+    roughness: f64,
+    metallic: f64,
+}
+
+struct Crossing {
+    distance: f64,
+    normal: Vec3,
+    face_normal: Vec3,
+}
+
+impl Heightfield {
+    fn new(
+        image: Arc<dyn Image + Send + Sync>,
+        settings: DeserializableHeightfield,
+    ) -> Heightfield {
+        let DeserializableHeightfield {
+            position,
+            width,
+            depth,
+            min_height,
+            max_height,
+            smooth,
+            closed,
+            albedo,
+            roughness,
+            metallic,
+            ..
+        } = settings;
+        let columns = image.width();
+        let rows = image.height();
+        let mut heights = Vec::with_capacity(columns * rows);
+        for row in 0..rows {
+            for column in 0..columns {
+                let [r, g, b] = image.get(column, row);
+                let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+                heights.push(min_height + (max_height - min_height) * luminance);
+            }
+        }
+
+        let cell_columns = columns - 1;
+        let cell_rows = rows - 1;
+        let mut cells = Level {
+            columns: cell_columns,
+            rows: cell_rows,
+            ranges: Vec::with_capacity(cell_columns * cell_rows),
+        };
+        for row in 0..cell_rows {
+            for column in 0..cell_columns {
+                let corners = [
+                    heights[row * columns + column],
+                    heights[row * columns + column + 1],
+                    heights[(row + 1) * columns + column],
+                    heights[(row + 1) * columns + column + 1],
+                ];
+                cells.ranges.push((
+                    corners.iter().copied().fold(f64::INFINITY, f64::min),
+                    corners.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                ));
+            }
+        }
+        let mut pyramid = vec![cells];
+        while pyramid.last().is_some_and(|l| l.columns > 1 || l.rows > 1) {
+            let below = pyramid.last().unwrap();
+            let mut level = Level {
+                columns: below.columns.div_ceil(2),
+                rows: below.rows.div_ceil(2),
+                ranges: Vec::new(),
+            };
+            for row in 0..level.rows {
+                for column in 0..level.columns {
+                    let mut range = (f64::INFINITY, f64::NEG_INFINITY);
+                    for r in (2 * row)..(2 * row + 2).min(below.rows) {
+                        for c in (2 * column)..(2 * column + 2).min(below.columns) {
+                            let (low, high) = below.ranges[r * below.columns + c];
+                            range = (range.0.min(low), range.1.max(high));
+                        }
+                    }
+                    level.ranges.push(range);
+                }
+            }
+            pyramid.push(level);
+        }
+
+        let mut heightfield = Heightfield {
+            position,
+            columns,
+            rows,
+            cell_width: width / cell_columns as f64,
+            cell_depth: depth / cell_rows as f64,
+            heights,
+            normals: Vec::new(),
+            bounds: Aabb::new(
+                Vec3::new(-0.5 * width, -0.5 * depth, min_height.min(max_height)),
+                Vec3::new(0.5 * width, 0.5 * depth, min_height.max(max_height)),
+            ),
+            pyramid,
+            smooth,
+            closed,
+            albedo,
+            roughness,
+            metallic,
+        };
+        heightfield.normals = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| heightfield.vertex_normal(column, row))
+            .collect();
+        heightfield
+    }
+
+    fn vertex(&self, column: usize, row: usize) -> Vec3 {
+        Vec3::new(
+            self.bounds.min.x + column as f64 * self.cell_width,
+            self.bounds.max.y - row as f64 * self.cell_depth,
+            self.heights[row * self.columns + column],
+        )
+    }
+
+    /// Normal from the central differences of the neighbouring heights.
+    fn vertex_normal(&self, column: usize, row: usize) -> Vec3 {
+        let left = self.vertex(column.saturating_sub(1), row);
+        let right = self.vertex((column + 1).min(self.columns - 1), row);
+        let top = self.vertex(column, row.saturating_sub(1));
+        let bottom = self.vertex(column, (row + 1).min(self.rows - 1));
+        let slope_x = (right.z - left.z) / (right.x - left.x);
+        let slope_y = (top.z - bottom.z) / (top.y - bottom.y);
+        Vec3::new(-slope_x, -slope_y, 1.0).normalize()
+    }
+
+    /// Bounds of the block of cells at `(column, row)` on a pyramid level.
+    fn block_bounds(&self, level: usize, column: usize, row: usize) -> Aabb {
+        let (low, high) = self.pyramid[level].ranges[row * self.pyramid[level].columns + column];
+        let cells = &self.pyramid[0];
+        let first_column = column << level;
+        let last_column = ((column + 1) << level).min(cells.columns);
+        let first_row = row << level;
+        let last_row = ((row + 1) << level).min(cells.rows);
+        Aabb::new(
+            Vec3::new(
+                self.bounds.min.x + first_column as f64 * self.cell_width,
+                self.bounds.max.y - last_row as f64 * self.cell_depth,
+                low,
+            ),
+            Vec3::new(
+                self.bounds.min.x + last_column as f64 * self.cell_width,
+                self.bounds.max.y - first_row as f64 * self.cell_depth,
+                high,
+            ),
+        )
+    }
+
+    /// Intersects both triangles of a cell, wound so their normals point up.
+    fn intersect_cell(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        column: usize,
+        row: usize,
+        crossings: &mut Vec<Crossing>,
+    ) {
+        let corners = [
+            (column, row),
+            (column + 1, row + 1),
+            (column + 1, row),
+            (column, row + 1),
+        ];
+        for [a, b, c] in [
+            [corners[0], corners[1], corners[2]],
+            [corners[0], corners[3], corners[1]],
+        ] {
+            let [pa, pb, pc] = [a, b, c].map(|(column, row)| self.vertex(column, row));
+            let edge1 = pb - pa;
+            let edge2 = pc - pa;
+
+            let p = direction.cross(edge2);
+            let determinant = edge1.dot(p);
+            if determinant.abs() < 1e-12 {
+                continue;
+            }
+            let inverse_determinant = 1.0 / determinant;
+
+            let s = origin - pa;
+            let u = s.dot(p) * inverse_determinant;
+            if !(0.0..=1.0).contains(&u) {
+                continue;
+            }
+            let q = s.cross(edge1);
+            let v = direction.dot(q) * inverse_determinant;
+            if v < 0.0 || u + v > 1.0 {
+                continue;
+            }
+            let distance = edge2.dot(q) * inverse_determinant;
+            if distance <= 0.0 {
+                continue;
+            }
+
+            let face_normal = edge1.cross(edge2);
+            let normal = if self.smooth {
+                let [na, nb, nc] =
+                    [a, b, c].map(|(column, row)| self.normals[row * self.columns + column]);
+                na * (1.0 - u - v) + nb * u + nc * v
+            } else {
+                face_normal
+            };
+            crossings.push(Crossing {
+                distance,
+                normal,
+                face_normal,
+            });
+        }
+    }
+
+    /// Height of the surface above a point inside the footprint.
+    fn height_at(&self, x: f64, y: f64) -> f64 {
+        let cells = &self.pyramid[0];
+        let u = (x - self.bounds.min.x) / self.cell_width;
+        let v = (self.bounds.max.y - y) / self.cell_depth;
+        let column = (u.floor().max(0.0) as usize).min(cells.columns - 1);
+        let row = (v.floor().max(0.0) as usize).min(cells.rows - 1);
+        let s = (u - column as f64).clamp(0.0, 1.0);
+        let t = (v - row as f64).clamp(0.0, 1.0);
+
+        let h = |c: usize, r: usize| self.heights[r * self.columns + c];
+        let h00 = h(column, row);
+        let h11 = h(column + 1, row + 1);
+        // Cells are split along the diagonal from (0, 0) to (1, 1)
+        if s >= t {
+            h00 + (h(column + 1, row) - h00) * s + (h11 - h(column + 1, row)) * t
+        } else {
+            h00 + (h(column, row + 1) - h00) * t + (h11 - h(column, row + 1)) * s
+        }
+    }
+
+    /// Outward normal of the side walls or bottom at a point on the bounding box.
+    fn wall_normal(&self, p: Vec3) -> Direction {
+        let center = self.bounds.center();
+        let half = (self.bounds.max - self.bounds.min) * 0.5;
+        let offsets = [
+            (p.x - center.x) / half.x,
+            (p.y - center.y) / half.y,
+            (p.z - center.z) / half.z.max(1e-12),
+        ];
+        let axis = (0..3)
+            .max_by(|&a, &b| offsets[a].abs().partial_cmp(&offsets[b].abs()).unwrap())
+            .unwrap();
+        let mut normal = [0.0; 3];
+        normal[axis] = offsets[axis].signum();
+        Direction::new(Vec3::new(normal[0], normal[1], normal[2]))
+    }
+
+    fn hit(&self, distance: f64, normal: Direction, is_front_face: bool) -> Hit {
+        Hit {
+            distance,
+            normal,
+            albedo: self.albedo,
+            is_front_face,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        }
+    }
+
+    fn surface_hit(&self, ray: Ray, crossing: &Crossing, is_front_face: bool) -> Hit {
+        let outwards = enhance_normal(
+            ray.direction,
+            Direction::new(crossing.face_normal),
+            is_front_face,
+        );
+        let normal = if crossing.normal.dot(*outwards) >= 0.0 {
+            crossing.normal
+        } else {
+            -crossing.normal
+        };
+        self.hit(crossing.distance, Direction::new(normal), is_front_face)
+    }
+}
+
+impl RTModel for Heightfield {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let origin = *(ray.origin - self.position);
+        let direction: Vec3 = *ray.direction;
+        let inverse_direction = Vec3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z);
+
+        let mut result = Vec::new();
+        let (enter, exit) = self.bounds.hit_range(origin, inverse_direction);
+        if enter > exit || exit < 0.0 {
+            return result;
+        }
+
+        let mut crossings = Vec::new();
+        let top = self.pyramid.len() - 1;
+        let mut stack = vec![(top, 0, 0)];
+        while let Some((level, column, row)) = stack.pop() {
+            let (near, far) = self
+                .block_bounds(level, column, row)
+                .hit_range(origin, inverse_direction);
+            if near > far || far < 0.0 {
+                continue;
+            }
+            if level == 0 {
+                self.intersect_cell(origin, direction, column, row, &mut crossings);
+                continue;
+            }
+            let below = &self.pyramid[level - 1];
+            for r in (2 * row)..(2 * row + 2).min(below.rows) {
+                for c in (2 * column)..(2 * column + 2).min(below.columns) {
+                    stack.push((level - 1, c, r));
+                }
+            }
+        }
+        crossings.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+
+        if !self.closed {
+            // A ray through a shared edge or vertex hits every triangle around it
+            crossings
+                .dedup_by(|b, a| (a.distance - b.distance).abs() <= 1e-9 * a.distance.max(1.0));
+            for crossing in &crossings {
+                result.push(self.surface_hit(ray, crossing, true));
+                result.push(self.surface_hit(ray, crossing, false));
+            }
+            return result;
+        }
+
+        // Inside the bounding box the solid is everything below the surface
+        let enter = enter.max(0.0);
+        let start = origin + direction * enter;
+        let mut inside = start.z <= self.height_at(start.x, start.y);
+        if inside {
+            let normal = if enter == 0.0 {
+                -ray.direction
+            } else {
+                self.wall_normal(start)
+            };
+            result.push(self.hit(enter, normal, true));
+        }
+        for crossing in &crossings {
+            // Going down through the surface enters the solid, duplicates on shared edges are skipped
+            let entering = crossing.face_normal.dot(direction) < 0.0;
+            if entering != inside && crossing.distance <= exit {
+                inside = entering;
+                result.push(self.surface_hit(ray, crossing, entering));
+            }
+        }
+        if inside {
+            result.push(self.hit(exit, self.wall_normal(origin + direction * exit), false));
+        }
+
+        result
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(self.bounds.translate(*self.position))
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Direction, Position, Vec3},
+    rt::Ray,
+};
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+use crate::{
+    deserialize::{
+        deserialize_ldr_color, deserialize_ldr_float, deserialize_optional_position,
+        deserialize_position,
+    },
+    json_schema::{LDRColorSchema, PositionSchema},
+};
+
+use super::{
+    expression::{self, Expression, Interval},
+    polynomial::Polynomial,
+    util::enhance_normal,
+    Hit, RTModel,
+};
+
+/// Rays are searched for crossings up to this distance.
+const MAX_DISTANCE: f64 = 1e3;
+/// Width below which a range of the ray is no longer split.
+const TOLERANCE: f64 = 1e-7;
+
+/// Surface where `equation` is zero, the points where it is negative being inside.
+///
+/// Polynomial equations are expanded and solved like a `polynomial` model, anything else is
+/// searched with interval arithmetic, which finds every crossing within 1000 units.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableImplicit {
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    /// Function of x, y and z like `sin(x) + sin(y) + sin(z)`, or `lhs = rhs`
+    equation: String,
+    /// Values of the other names used in `equation`
+    #[serde(default)]
+    parameters: HashMap<String, f64>,
+    /// Point inside the solid, the points where the equation has its sign are inside.
+    /// When not given, the points where the equation is negative are inside
+    #[serde(default, deserialize_with = "deserialize_optional_position")]
+    #[schemars(with = "Option<PositionSchema>")]
+    inside: Option<Position>,
+    #[serde(default, deserialize_with = "deserialize_ldr_color")]
+    #[schemars(with = "LDRColorSchema")]
+    albedo: LDRColor,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: f64,
+}
+
+impl DeserializableImplicit {
+    pub fn into_rt_model(self) -> Box<dyn RTModel + Send + Sync> {
+        let mut expression = expression::parse(&self.equation, &self.parameters)
+            .unwrap_or_else(|e| panic!("Invalid equation \"{}\": {}", self.equation, e));
+
+        if let Some(terms) = expression.to_terms() {
+            return Box::new(Polynomial::new(
+                self.position,
+                terms,
+                self.inside,
+                self.albedo,
+                self.roughness,
+                self.metallic,
+            ));
+        }
+
+        if let Some(inside) = self.inside {
+            if expression.evaluate(*(inside - self.position)) > 0.0 {
+                expression = Expression::Negate(Box::new(expression));
+            }
+        }
+        Box::new(Implicit {
+            position: self.position,
+            expression,
+            albedo: self.albedo,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        })
+    }
+}
+
+struct Implicit {
+    position: Position,
+    expression: Expression,
+    albedo: LDRColor,
+    roughness: f64,
+    metallic: f64,
+}
+
+impl Implicit {
+    fn hit(&self, distance: f64, normal: Direction, is_front_face: bool) -> Hit {
+        Hit {
+            distance,
+            normal,
+            albedo: self.albedo,
+            is_front_face,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        }
+    }
+
+    fn is_inside(&self, position: Vec3) -> bool {
+        self.expression.evaluate(position) < 0.0
+    }
+
+    /// Gradient by central differences.
+    fn normal(&self, position: Vec3) -> Direction {
+        let h = 1e-6 * position.length().max(1.0);
+        let difference = |offset: Vec3| {
+            self.expression.evaluate(position + offset)
+                - self.expression.evaluate(position - offset)
+        };
+        Direction::new(Vec3::new(
+            difference(Vec3::new(h, 0.0, 0.0)),
+            difference(Vec3::new(0.0, h, 0.0)),
+            difference(Vec3::new(0.0, 0.0, h)),
+        ))
+    }
+
+    /// Bisects a sign change between `lo` and `hi`.
+    fn refine(&self, origin: Vec3, direction: Vec3, mut lo: f64, mut hi: f64) -> f64 {
+        let lo_is_inside = self.is_inside(origin + direction * lo);
+        for _ in 0..30 {
+            let middle = 0.5 * (lo + hi);
+            if self.is_inside(origin + direction * middle) == lo_is_inside {
+                lo = middle;
+            } else {
+                hi = middle;
+            }
+        }
+        0.5 * (lo + hi)
+    }
+
+    /// Ascending distances where the sign of the field changes along the ray.
+    ///
+    /// Ranges over which the field provably keeps its sign are dropped,
+    /// the others halved until they are small enough to bisect.
+    fn crossings(&self, origin: Vec3, direction: Vec3) -> Vec<f64> {
+        let mut crossings = Vec::new();
+        let mut stack = vec![(0.0, MAX_DISTANCE)];
+        while let Some((lo, hi)) = stack.pop() {
+            let range = |axis: usize| {
+                let (a, b) = (
+                    origin[axis] + direction[axis] * lo,
+                    origin[axis] + direction[axis] * hi,
+                );
+                Interval::new(a.min(b), a.max(b))
+            };
+            let values = self
+                .expression
+                .evaluate_interval([range(0), range(1), range(2)]);
+            if !values.contains(0.0) {
+                continue;
+            }
+            if hi - lo <= TOLERANCE * lo.max(1.0) {
+                if self.is_inside(origin + direction * lo)
+                    != self.is_inside(origin + direction * hi)
+                {
+                    crossings.push(self.refine(origin, direction, lo, hi));
+                }
+                continue;
+            }
+            let middle = 0.5 * (lo + hi);
+            stack.push((middle, hi));
+            stack.push((lo, middle));
+        }
+        crossings
+    }
+}
+
+impl RTModel for Implicit {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let origin = *(ray.origin - self.position);
+        let direction: Vec3 = *ray.direction;
+
+        let mut result = Vec::new();
+
+        let mut is_front_face = false;
+        if self.is_inside(origin) {
+            is_front_face = true;
+            result.push(self.hit(0.0, -ray.direction, true));
+        }
+        for distance in self.crossings(origin, direction) {
+            is_front_face = !is_front_face;
+            let normal = self.normal(origin + direction * distance);
+            result.push(self.hit(
+                distance,
+                enhance_normal(ray.direction, normal, is_front_face),
+                is_front_face,
+            ));
+        }
+        if is_front_face {
+            result.push(self.hit(f64::INFINITY, ray.direction, false));
+        }
+
+        result
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        None
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Move},
+    rt::Ray,
+};
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+use crate::{
+    deserialize::{
+        deserialize_move, deserialize_optional_ldr_color, deserialize_optional_ldr_float,
+        deserialize_scale,
+    },
+    json_schema::{LDRColorSchema, MoveSchema, Scale},
+    BuildContext, ImageLoader, MeshLoader,
+};
+
+use super::{
+    transform::{affine_matrix, Rotation, Shear, Transform},
+    Hit, RTModel,
+};
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableInstance {
+    /// Name of the model in the scene's `definitions`
+    name: String,
+    #[serde(default, deserialize_with = "deserialize_move")]
+    #[schemars(with = "MoveSchema")]
+    translation: Move,
+    #[serde(default)]
+    rotation: Option<Rotation>,
+    #[serde(default, deserialize_with = "deserialize_scale")]
+    scale: Scale,
+    #[serde(default)]
+    shear: Shear,
+    /// Rows of an affine matrix, the last of which must be [0, 0, 0, 1]
+    #[serde(default)]
+    matrix: Option<[[f64; 4]; 4]>,
+    #[serde(default, deserialize_with = "deserialize_optional_ldr_color")]
+    #[schemars(with = "Option<LDRColorSchema>")]
+    albedo: Option<LDRColor>,
+    #[serde(default, deserialize_with = "deserialize_optional_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: Option<f64>,
+    #[serde(default, deserialize_with = "deserialize_optional_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: Option<f64>,
+}
+
+impl DeserializableInstance {
+    pub fn into_rt_model<T: ImageLoader, M: MeshLoader>(
+        self,
+        context: &mut BuildContext<T, M>,
+    ) -> Box<dyn RTModel + Send + Sync> {
+        let model = context.load_model(&self.name);
+        let model: Box<dyn RTModel + Send + Sync> =
+            if self.albedo.is_none() && self.roughness.is_none() && self.metallic.is_none() {
+                Box::new(model)
+            } else {
+                Box::new(MaterialOverride {
+                    model,
+                    albedo: self.albedo,
+                    roughness: self.roughness,
+                    metallic: self.metallic,
+                })
+            };
+
+        let matrix = affine_matrix(
+            self.translation,
+            self.rotation.as_ref(),
+            &self.shear,
+            self.scale,
+            self.matrix,
+        );
+        Box::new(Transform::new(model, matrix))
+    }
+}
+
+struct MaterialOverride {
+    model: Arc<dyn RTModel + Send + Sync>,
+    albedo: Option<LDRColor>,
+    roughness: Option<f64>,
+    metallic: Option<f64>,
+}
+
+impl RTModel for MaterialOverride {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        self.model
+            .test(ray)
+            .into_iter()
+            .map(|hit| Hit {
+                albedo: self.albedo.unwrap_or(hit.albedo),
+                roughness: self.roughness.unwrap_or(hit.roughness),
+                metallic: self.metallic.unwrap_or(hit.metallic),
+                ..hit
+            })
+            .collect()
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.model.bounds()
+    }
+}
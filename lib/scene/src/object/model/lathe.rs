@@ -0,0 +1,186 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Direction, Position, Vec3},
+    rt::Ray,
+};
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+use crate::{
+    deserialize::{
+        deserialize_direction, deserialize_ldr_color, deserialize_ldr_float, deserialize_position,
+    },
+    json_schema::{DirectionSchema, LDRColorSchema, PositionSchema},
+};
+
+use super::{
+    solver::roots_in_range,
+    util::{disk_bounds, enhance_normal, orthonormal_basis, up},
+    Hit, RTModel,
+};
+
+/// Solid swept by a closed profile revolving around an axis.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableLathe {
+    /// Point of the axis at height 0 of the profile
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    #[serde(default = "up", deserialize_with = "deserialize_direction")]
+    #[schemars(with = "DirectionSchema")]
+    axis: Direction,
+    /// Polygon of `[radius, height]` points, closed from the last point back to the first
+    profile: Vec<[f64; 2]>,
+    #[serde(default, deserialize_with = "deserialize_ldr_color")]
+    #[schemars(with = "LDRColorSchema")]
+    albedo: LDRColor,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: f64,
+}
+
+impl DeserializableLathe {
+    pub fn into_rt_model(self) -> Box<dyn RTModel + Send + Sync> {
+        if self.profile.len() < 3 {
+            panic!("A lathe profile needs at least 3 points");
+        }
+        if self.profile.iter().any(|[radius, _]| *radius < 0.0) {
+            panic!("Lathe profile radii must be nonnegative");
+        }
+
+        let (u, v) = orthonormal_basis(self.axis);
+        Box::new(Lathe {
+            position: self.position,
+            u,
+            v,
+            axis: self.axis,
+            profile: self.profile,
+            albedo: self.albedo,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        })
+    }
+}
+
+struct Lathe {
+    position: Position,
+    u: Vec3,
+    v: Vec3,
+    axis: Direction,
+    profile: Vec<[f64; 2]>,
+    albedo: LDRColor,
+    roughness: f64,
+    metallic: f64,
+}
+
+impl Lathe {
+    fn hit(&self, distance: f64, normal: Direction, is_front_face: bool) -> Hit {
+        Hit {
+            distance,
+            normal,
+            albedo: self.albedo,
+            is_front_face,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        }
+    }
+
+    /// Every crossing ahead of the ray as (distance, normal in the local frame).
+    ///
+    /// Each segment of the profile revolves into a cone, cylinder or flat ring. Segments own
+    /// their first point but not their last, so a ray through a shared circle counts once.
+    fn crossings(&self, origin: Vec3, direction: Vec3) -> Vec<(f64, Vec3)> {
+        let (ox, oy, oz) = (origin.x, origin.y, origin.z);
+        let (dx, dy, dz) = (direction.x, direction.y, direction.z);
+
+        let mut crossings = Vec::new();
+        for (i, &[r0, h0]) in self.profile.iter().enumerate() {
+            let [r1, h1] = self.profile[(i + 1) % self.profile.len()];
+
+            if h0 == h1 {
+                if r0 == r1 || dz == 0.0 {
+                    continue;
+                }
+                let t = (h0 - oz) / dz;
+                let radius = (ox + t * dx).hypot(oy + t * dy);
+                let s = (radius - r0) / (r1 - r0);
+                if t > 0.0 && (0.0..1.0).contains(&s) {
+                    crossings.push((t, Vec3::Z));
+                }
+                continue;
+            }
+
+            // Radius along the segment: r(z) = r0 + k * (z - h0)
+            let k = (r1 - r0) / (h1 - h0);
+            let r = r0 + k * (oz - h0);
+            let a = dx * dx + dy * dy - k * k * dz * dz;
+            let b = 2.0 * (ox * dx + oy * dy - k * r * dz);
+            let c = ox * ox + oy * oy - r * r;
+            for t in roots_in_range(&[c, b, a], 0.0, f64::INFINITY) {
+                let z = oz + t * dz;
+                let s = (z - h0) / (h1 - h0);
+                if t > 0.0 && (0.0..1.0).contains(&s) {
+                    let radius = r0 + k * (z - h0);
+                    crossings.push((t, Vec3::new(ox + t * dx, oy + t * dy, -k * radius)));
+                }
+            }
+        }
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+        crossings
+    }
+}
+
+impl RTModel for Lathe {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        // Work in the local frame where the axis is z and the profile's height 0 at z = 0
+        let origin = ray.origin - self.position;
+        let origin = Vec3::new(
+            origin.dot(self.u),
+            origin.dot(self.v),
+            origin.dot(*self.axis),
+        );
+        let direction: Vec3 = *ray.direction;
+        let direction = Vec3::new(
+            direction.dot(self.u),
+            direction.dot(self.v),
+            direction.dot(*self.axis),
+        );
+        let to_world = |n: Vec3| Direction::new(self.u * n.x + self.v * n.y + *self.axis * n.z);
+
+        let crossings = self.crossings(origin, direction);
+
+        let mut result = Vec::new();
+        // Parity of the crossings ahead tells whether the ray starts inside
+        let mut is_front_face = crossings.len() % 2 == 1;
+        if is_front_face {
+            result.push(self.hit(0.0, -ray.direction, true));
+        }
+        for (distance, normal) in crossings {
+            is_front_face = !is_front_face;
+            result.push(self.hit(
+                distance,
+                enhance_normal(ray.direction, to_world(normal), is_front_face),
+                is_front_face,
+            ));
+        }
+
+        result
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let radius = self.profile.iter().map(|p| p[0]).fold(0.0, f64::max);
+        let (bottom, top) = self
+            .profile
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| {
+                (lo.min(p[1]), hi.max(p[1]))
+            });
+        let bottom = *self.position + *self.axis * bottom;
+        let top = *self.position + *self.axis * top;
+        Some(disk_bounds(bottom, self.axis, radius).union(disk_bounds(top, self.axis, radius)))
+    }
+}
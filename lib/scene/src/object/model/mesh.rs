@@ -0,0 +1,333 @@
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Direction, Position, Vec3},
+    rt::Ray,
+};
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+use crate::{
+    deserialize::{deserialize_ldr_color, deserialize_ldr_float, deserialize_position},
+    json_schema::{LDRColorSchema, PositionSchema},
+    mesh::MeshGeometry,
+    texture::{DeserializableTexture, Texture},
+    BuildContext, ImageLoader, MeshLoader,
+};
+
+use super::{Hit, RTModel};
+
+fn default_smooth() -> bool {
+    true
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableMesh {
+    /// OBJ, STL or PLY file, relative to the scene file
+    path: String,
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    /// Interpolate the file's vertex normals instead of using flat faces
+    #[serde(default = "default_smooth")]
+    smooth: bool,
+    /// Used unless the file has vertex colors or a texture is given
+    #[serde(default, deserialize_with = "deserialize_ldr_color")]
+    #[schemars(with = "LDRColorSchema")]
+    albedo: LDRColor,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: f64,
+    /// Sampled with the mesh's texture coordinates
+    #[serde(default)]
+    texture: Option<DeserializableTexture>,
+}
+
+impl DeserializableMesh {
+    pub fn into_rt_model<T: ImageLoader, M: MeshLoader>(
+        self,
+        context: &mut BuildContext<T, M>,
+    ) -> Box<dyn RTModel + Send + Sync> {
+        Box::new(Mesh {
+            geometry: context.mesh_cache.load(&self.path),
+            position: self.position,
+            smooth: self.smooth,
+            albedo: self.albedo,
+            roughness: self.roughness,
+            metallic: self.metallic,
+            texture: self
+                .texture
+                .map(|t| t.into_texture(&mut context.image_cache)),
+        })
+    }
+}
+
+struct Mesh {
+    geometry: Arc<MeshGeometry>,
+    position: Position,
+    smooth: bool,
+    albedo: LDRColor,
+    roughness: f64,
+    metallic: f64,
+    texture: Option<Arc<dyn Texture + Send + Sync>>,
+}
+
+struct Crossing {
+    distance: f64,
+    triangle: usize,
+    u: f64,
+    v: f64,
+    /// Whether the ray runs against the triangle's winding normal
+    entering: bool,
+}
+
+impl Mesh {
+    /// Möller–Trumbore intersection, accepting both sides of the triangle.
+    fn intersect(&self, origin: Vec3, direction: Vec3, triangle: usize) -> Option<Crossing> {
+        let mesh = &self.geometry.mesh;
+        let [a, b, c] = mesh.triangles[triangle]
+            .positions
+            .map(|i| mesh.positions[i]);
+        let edge1 = b - a;
+        let edge2 = c - a;
+
+        let p = direction.cross(edge2);
+        let determinant = edge1.dot(p);
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+        let inverse_determinant = 1.0 / determinant;
+
+        let s = origin - a;
+        let u = s.dot(p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(edge1);
+        let v = direction.dot(q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let distance = edge2.dot(q) * inverse_determinant;
+        if distance <= 0.0 {
+            return None;
+        }
+
+        Some(Crossing {
+            distance,
+            triangle,
+            u,
+            v,
+            entering: determinant > 0.0,
+        })
+    }
+
+    fn hit(&self, ray: Ray, crossing: &Crossing, is_front_face: bool) -> Hit {
+        let mesh = &self.geometry.mesh;
+        let triangle = &mesh.triangles[crossing.triangle];
+        let weights = [1.0 - crossing.u - crossing.v, crossing.u, crossing.v];
+
+        let [a, b, c] = triangle.positions.map(|i| mesh.positions[i]);
+        let face_normal = (b - a).cross(c - a);
+        let normal = match triangle.normals {
+            Some(normals) if self.smooth => normals
+                .iter()
+                .zip(weights)
+                .fold(Vec3::new(0.0, 0.0, 0.0), |sum, (&i, w)| {
+                    sum + mesh.normals[i] * w
+                }),
+            _ => face_normal,
+        };
+        // Orient the shading normal by the geometric one, so it points outwards for front faces
+        let outwards = (ray.direction.dot(Direction::new(face_normal)) < 0.0) == is_front_face;
+        let normal = if outwards == (normal.dot(face_normal) >= 0.0) {
+            normal
+        } else {
+            -normal
+        };
+
+        let albedo =
+            match (&self.texture, triangle.uvs) {
+                (Some(texture), Some(uvs)) => {
+                    let [u, v] = uvs.iter().zip(weights).fold([0.0, 0.0], |sum, (&i, w)| {
+                        [sum[0] + mesh.uvs[i][0] * w, sum[1] + mesh.uvs[i][1] * w]
+                    });
+                    // Texture coordinates grow upwards, image rows downwards
+                    texture.get(u.rem_euclid(1.0), (1.0 - v).rem_euclid(1.0))
+                }
+                _ if !mesh.colors.is_empty() => {
+                    let color = triangle.positions.iter().zip(weights).fold(
+                        [0.0, 0.0, 0.0],
+                        |sum, (&i, w)| {
+                            let c = mesh.colors[i];
+                            [sum[0] + c.r * w, sum[1] + c.g * w, sum[2] + c.b * w]
+                        },
+                    );
+                    LDRColor {
+                        r: color[0],
+                        g: color[1],
+                        b: color[2],
+                    }
+                }
+                _ => self.albedo,
+            };
+
+        Hit {
+            distance: crossing.distance,
+            normal: Direction::new(normal),
+            albedo,
+            is_front_face,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        }
+    }
+}
+
+impl RTModel for Mesh {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let origin = *(ray.origin - self.position);
+        let direction: Vec3 = *ray.direction;
+
+        let mut crossings = Vec::new();
+        let local_ray = Ray {
+            origin: Position::new(origin),
+            ..ray
+        };
+        self.geometry
+            .bvh
+            .traverse(local_ray, f64::INFINITY, |triangle, limit| {
+                crossings.extend(self.intersect(origin, direction, triangle));
+                limit
+            });
+        crossings.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+        // A ray through a shared edge or vertex hits every triangle around it
+        crossings.dedup_by(|b, a| {
+            a.entering == b.entering
+                && (a.distance - b.distance).abs() <= 1e-9 * a.distance.max(1.0)
+        });
+
+        let mut result = Vec::new();
+        if !self.geometry.closed {
+            // Open surfaces have no inside, every crossing enters and leaves at once
+            for crossing in &crossings {
+                result.push(self.hit(ray, crossing, true));
+                result.push(self.hit(ray, crossing, false));
+            }
+            return result;
+        }
+
+        // Parity of the remaining crossings tells whether the ray starts inside
+        let mut is_front_face = crossings.len() % 2 == 1;
+        if is_front_face {
+            result.push(Hit {
+                distance: 0.0,
+                normal: -ray.direction,
+                albedo: self.albedo,
+                is_front_face: true,
+                roughness: self.roughness,
+                metallic: self.metallic,
+            });
+        }
+        for crossing in &crossings {
+            is_front_face = !is_front_face;
+            result.push(self.hit(ray, crossing, is_front_face));
+        }
+
+        result
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(self.geometry.bvh.bounds().translate(*self.position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use seui_engine_raytracing_csg_renderer_core::types::{
+        math::{Direction, Position, Vec3},
+        rt::Ray,
+    };
+    use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+    use crate::mesh::{obj, MeshGeometry};
+
+    use super::{Mesh, RTModel};
+
+    /// Unit cube with outward winding, whose quads split along a diagonal through their centers.
+    const CUBE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                        v 0 0 1\nv 1 0 1\nv 1 1 1\nv 0 1 1\n\
+                        f 1 4 3 2\nf 1 2 6 5\nf 4 8 7 3\nf 1 5 8 4\nf 2 3 7 6\n";
+    const TOP: &str = "f 5 6 7 8\n";
+
+    fn mesh(content: &str) -> Mesh {
+        Mesh {
+            geometry: Arc::new(MeshGeometry::new(obj::parse(content).unwrap())),
+            position: Position::new(Vec3::new(0.0, 0.0, 0.0)),
+            smooth: false,
+            albedo: LDRColor::default(),
+            roughness: 0.0,
+            metallic: 0.0,
+            texture: None,
+        }
+    }
+
+    fn assert_hits(mesh: &Mesh, origin: [f64; 3], direction: [f64; 3], expected: &[(f64, bool)]) {
+        let hits = mesh.test(Ray {
+            origin: Position::new(Vec3::new(origin[0], origin[1], origin[2])),
+            direction: Direction::new(Vec3::new(direction[0], direction[1], direction[2])),
+            time: 0.0,
+        });
+        let actual: Vec<(f64, bool)> = hits
+            .iter()
+            .map(|hit| (hit.distance, hit.is_front_face))
+            .collect();
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (&(distance, is_front_face), &(expected_distance, expected_front)) in
+            actual.iter().zip(expected)
+        {
+            assert!((distance - expected_distance).abs() < 1e-9, "{:?}", actual);
+            assert_eq!(is_front_face, expected_front, "{:?}", actual);
+        }
+    }
+
+    #[test]
+    fn closed_cube_is_hit_once_per_side() {
+        let cube = mesh(&(CUBE.to_string() + TOP));
+        assert!(cube.geometry.closed);
+        // Through the diagonals at the centers of the bottom and top faces
+        assert_hits(
+            &cube,
+            [0.5, 0.5, -1.0],
+            [0.0, 0.0, 1.0],
+            &[(1.0, true), (2.0, false)],
+        );
+        // Through the edge between the bottom and front faces, and out the top and back ones
+        let diagonal = 2f64.sqrt();
+        assert_hits(
+            &cube,
+            [0.5, -1.0, -1.0],
+            [0.0, 1.0, 1.0],
+            &[(diagonal, true), (2.0 * diagonal, false)],
+        );
+    }
+
+    #[test]
+    fn open_box_is_a_surface() {
+        let open = mesh(CUBE);
+        assert!(!open.geometry.closed);
+        // Every wall is entered and left at once, with nothing inside
+        assert_hits(
+            &open,
+            [0.5, -1.0, 0.5],
+            [0.0, 1.0, 0.0],
+            &[(1.0, true), (1.0, false), (2.0, true), (2.0, false)],
+        );
+    }
+}
@@ -0,0 +1,222 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Direction, Position, Vec3},
+    rt::Ray,
+};
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+use crate::{
+    deserialize::{
+        deserialize_ldr_color, deserialize_ldr_float, deserialize_nonnegative_float,
+        deserialize_position,
+    },
+    json_schema::{LDRColorSchema, PositionSchema},
+};
+
+use super::{
+    solver::{multiply, roots_in_range},
+    util::one,
+    Hit, RTModel,
+};
+
+fn default_threshold() -> f64 {
+    0.5
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Metaball {
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    /// Radius of influence, beyond which the ball adds nothing to the field
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    radius: f64,
+    /// Negative weights carve into the other balls
+    #[serde(default = "one")]
+    weight: f64,
+}
+
+/// Surface where the summed field of the balls reaches `threshold`.
+///
+/// Each ball contributes `weight * (1 - (d / radius)^2)^3` at distance `d`, so a lone ball
+/// of weight 1 has a surface radius of `radius * sqrt(1 - threshold^(1/3))`.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Metaballs {
+    balls: Vec<Metaball>,
+    #[serde(default = "default_threshold")]
+    threshold: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_color")]
+    #[schemars(with = "LDRColorSchema")]
+    albedo: LDRColor,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: f64,
+}
+
+impl Metaballs {
+    pub fn into_rt_model(self) -> Box<dyn RTModel + Send + Sync> {
+        if self.threshold <= 0.0 {
+            panic!("The metaball threshold must be positive");
+        }
+        if self.balls.iter().any(|ball| ball.radius == 0.0) {
+            panic!("Metaball radii must be positive");
+        }
+        Box::new(self)
+    }
+
+    fn hit(&self, distance: f64, normal: Direction, is_front_face: bool) -> Hit {
+        Hit {
+            distance,
+            normal,
+            albedo: self.albedo,
+            is_front_face,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        }
+    }
+
+    fn field(&self, position: Vec3) -> f64 {
+        self.balls
+            .iter()
+            .map(|ball| {
+                let s = (position - *ball.position).length_square() / (ball.radius * ball.radius);
+                if s < 1.0 {
+                    ball.weight * (1.0 - s).powi(3)
+                } else {
+                    0.0
+                }
+            })
+            .sum()
+    }
+
+    /// Outward normal, against the gradient of the field.
+    fn normal(&self, position: Vec3) -> Direction {
+        let gradient = self
+            .balls
+            .iter()
+            .fold(Vec3::new(0.0, 0.0, 0.0), |sum, ball| {
+                let offset = position - *ball.position;
+                let r2 = ball.radius * ball.radius;
+                let s = offset.length_square() / r2;
+                if s < 1.0 {
+                    sum + offset * (-6.0 * ball.weight * (1.0 - s).powi(2) / r2)
+                } else {
+                    sum
+                }
+            });
+        Direction::new(-gradient)
+    }
+
+    /// Distances along the ray where it enters and leaves each ball's radius of influence.
+    fn spans(&self, origin: Vec3, direction: Vec3) -> Vec<(&Metaball, f64, f64)> {
+        self.balls
+            .iter()
+            .filter_map(|ball| {
+                let offset = origin - *ball.position;
+                let b = offset.dot(direction);
+                let c = offset.length_square() - ball.radius * ball.radius;
+                let discriminant = b * b - c;
+                if discriminant <= 0.0 {
+                    return None;
+                }
+                let root = discriminant.sqrt();
+                Some((ball, -b - root, -b + root))
+            })
+            .collect()
+    }
+}
+
+impl RTModel for Metaballs {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let mut result = Vec::new();
+
+        let origin: Vec3 = *ray.origin;
+        let direction: Vec3 = *ray.direction;
+        let spans = self.spans(origin, direction);
+
+        // Between consecutive span ends the same balls are active and the field is a
+        // single polynomial of degree 6 in the distance
+        let mut breakpoints: Vec<f64> = spans
+            .iter()
+            .flat_map(|&(_, enter, exit)| [enter, exit])
+            .filter(|&t| t > 0.0)
+            .collect();
+        breakpoints.push(0.0);
+        breakpoints.sort_by(f64::total_cmp);
+        breakpoints.dedup();
+
+        let mut is_front_face = false;
+        if self.field(origin) >= self.threshold {
+            is_front_face = true;
+            result.push(self.hit(0.0, -ray.direction, true));
+        }
+
+        for window in breakpoints.windows(2) {
+            let (lo, hi) = (window[0], window[1]);
+            let middle = 0.5 * (lo + hi);
+            // Restart the ray at the segment to keep the coefficients small
+            let start = origin + direction * lo;
+            let mut coefficients = vec![-self.threshold, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+            for &(ball, enter, exit) in &spans {
+                if middle < enter || middle > exit {
+                    continue;
+                }
+                // 1 - |start + u d - c|^2 / r^2 as a polynomial in u, cubed
+                let r2 = ball.radius * ball.radius;
+                let offset = start - *ball.position;
+                let falloff = [
+                    1.0 - offset.length_square() / r2,
+                    -2.0 * offset.dot(direction) / r2,
+                    -1.0 / r2,
+                ];
+                let cubed = multiply(&multiply(&falloff, &falloff), &falloff);
+                for (c, term) in coefficients.iter_mut().zip(cubed) {
+                    *c += ball.weight * term;
+                }
+            }
+
+            for (i, u) in roots_in_range(&coefficients, 0.0, hi - lo)
+                .into_iter()
+                .enumerate()
+            {
+                let t = lo + u;
+                // A root on the breakpoint was already found at the end of the previous segment
+                let is_repeated = i == 0
+                    && result
+                        .last()
+                        .is_some_and(|hit: &Hit| (hit.distance - t).abs() <= 1e-9 * t.max(1.0));
+                if t <= 0.0 || is_repeated {
+                    continue;
+                }
+                is_front_face = !is_front_face;
+                let normal = self.normal(origin + direction * t);
+                result.push(self.hit(t, normal, is_front_face));
+            }
+        }
+        if is_front_face {
+            // Lost a crossing to round-off, close the solid where the last ball ends
+            let exit = breakpoints.last().copied().unwrap_or(0.0);
+            result.push(self.hit(exit, ray.direction, false));
+        }
+
+        result
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        // Only balls with positive weight can raise the field to the threshold
+        Some(self.balls.iter().filter(|ball| ball.weight > 0.0).fold(
+            Aabb::EMPTY,
+            |bounds, ball| {
+                let extent = Vec3::new(ball.radius, ball.radius, ball.radius);
+                bounds.union(Aabb::new(*ball.position - extent, *ball.position + extent))
+            },
+        ))
+    }
+}
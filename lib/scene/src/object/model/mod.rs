@@ -1,24 +1,68 @@
+use std::sync::Arc;
+
+use bezier_patch::DeserializableBezierPatch;
+use cone::DeserializableCone;
 use csg::{DeserializableDifference, DeserializableIntersection, DeserializableUnion};
 use cube::Cube;
+use cylinder::DeserializableCylinder;
+use extrude::DeserializableExtrude;
+use flat::{DeserializableDisk, DeserializableRectangle, DeserializableTriangle};
+use heightfield::DeserializableHeightfield;
+use implicit::DeserializableImplicit;
+use instance::DeserializableInstance;
+use lathe::DeserializableLathe;
+use mesh::DeserializableMesh;
+use metaballs::Metaballs;
+use motion::DeserializableMotion;
 use plane::Plane;
+use polynomial::DeserializablePolynomial;
 use quadratic::Quadratic;
 use quadric::Quadric;
-use quartic::Quartic;
+use quartic::DeserializableQuartic;
 use schemars::JsonSchema;
+use sdf::{DeserializableSdf, Sdf};
 use serde::Deserialize;
-use seui_engine_raytracing_csg_renderer_core::types::{math::Direction, rt::Ray};
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Direction},
+    rt::Ray,
+};
 use seui_engine_raytracing_csg_renderer_types::LDRColor;
+use smooth::{DeserializableSmoothOperation, SmoothOperator};
 use sphere::DeserializableSphere;
+use superellipsoid::DeserializableSuperellipsoid;
+use torus::DeserializableTorus;
+use transform::DeserializableTransform;
 
-use crate::{ImageCache, ImageLoader};
+use crate::{BuildContext, ImageLoader, MeshLoader};
 
+pub mod bezier_patch;
+pub mod clip;
+pub mod cone;
 pub mod csg;
 pub mod cube;
+pub mod cylinder;
+pub mod expression;
+pub mod extrude;
+pub mod flat;
+pub mod heightfield;
+pub mod implicit;
+pub mod instance;
+pub mod lathe;
+pub mod mesh;
+pub mod metaballs;
+pub mod motion;
 pub mod plane;
+pub mod polynomial;
 pub mod quadratic;
 pub mod quadric;
 pub mod quartic;
+pub mod sdf;
+pub mod smooth;
+pub mod solver;
 pub mod sphere;
+pub mod superellipsoid;
+pub mod torus;
+pub mod transform;
 pub mod util;
 
 #[derive(Clone, Debug)]
@@ -32,7 +76,21 @@ pub struct Hit {
 }
 
 pub trait RTModel {
+    /// Hits ahead of the ray in ascending distance, alternately entering and leaving the model.
     fn test(&self, ray: Ray) -> Vec<Hit>;
+
+    /// Box enclosing every hit the model can report, `None` if it is unbounded.
+    fn bounds(&self) -> Option<Aabb>;
+}
+
+impl<T: RTModel + ?Sized> RTModel for Arc<T> {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        (**self).test(ray)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        (**self).bounds()
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, JsonSchema)]
@@ -41,29 +99,110 @@ pub enum DeserializableRTModel {
     Union(DeserializableUnion),
     Intersection(DeserializableIntersection),
     Difference(DeserializableDifference),
+    SmoothUnion(DeserializableSmoothOperation),
+    SmoothIntersection(DeserializableSmoothOperation),
+    SmoothDifference(DeserializableSmoothOperation),
+    Motion(DeserializableMotion),
+    Transform(DeserializableTransform),
+    Instance(DeserializableInstance),
     Sphere(DeserializableSphere),
     Plane(Plane),
     Cube(Cube),
+    Cylinder(DeserializableCylinder),
+    Cone(DeserializableCone),
     Quadric(Quadric),
     Quadratic(Quadratic),
-    Quartic(Quartic),
+    Quartic(DeserializableQuartic),
+    Polynomial(DeserializablePolynomial),
+    Implicit(DeserializableImplicit),
+    Torus(DeserializableTorus),
+    Mesh(DeserializableMesh),
+    Sdf(DeserializableSdf),
+    Metaballs(Metaballs),
+    Superellipsoid(DeserializableSuperellipsoid),
+    Heightfield(DeserializableHeightfield),
+    BezierPatch(DeserializableBezierPatch),
+    Lathe(DeserializableLathe),
+    Extrude(DeserializableExtrude),
+    Disk(DeserializableDisk),
+    Rectangle(DeserializableRectangle),
+    Triangle(DeserializableTriangle),
 }
 
 impl DeserializableRTModel {
-    pub fn into_rt_model<T: ImageLoader>(
+    pub fn into_rt_model<T: ImageLoader, M: MeshLoader>(
         self,
-        image_cache: &mut ImageCache<T>,
+        context: &mut BuildContext<T, M>,
     ) -> Box<dyn RTModel + Send + Sync> {
         match self {
-            DeserializableRTModel::Union(o) => o.into_rt_model(image_cache),
-            DeserializableRTModel::Intersection(o) => o.into_rt_model(image_cache),
-            DeserializableRTModel::Difference(o) => o.into_rt_model(image_cache),
-            DeserializableRTModel::Sphere(o) => o.into_rt_model(image_cache),
+            DeserializableRTModel::Union(o) => o.into_rt_model(context),
+            DeserializableRTModel::Intersection(o) => o.into_rt_model(context),
+            DeserializableRTModel::Difference(o) => o.into_rt_model(context),
+            DeserializableRTModel::SmoothUnion(o) => {
+                o.into_rt_model(SmoothOperator::Union, context)
+            }
+            DeserializableRTModel::SmoothIntersection(o) => {
+                o.into_rt_model(SmoothOperator::Intersection, context)
+            }
+            DeserializableRTModel::SmoothDifference(o) => {
+                o.into_rt_model(SmoothOperator::Difference, context)
+            }
+            DeserializableRTModel::Motion(o) => o.into_rt_model(context),
+            DeserializableRTModel::Transform(o) => o.into_rt_model(context),
+            DeserializableRTModel::Instance(o) => o.into_rt_model(context),
+            DeserializableRTModel::Sphere(o) => o.into_rt_model(context),
             DeserializableRTModel::Plane(o) => Box::new(o),
             DeserializableRTModel::Cube(o) => Box::new(o),
+            DeserializableRTModel::Cylinder(o) => o.into_rt_model(),
+            DeserializableRTModel::Cone(o) => o.into_rt_model(),
             DeserializableRTModel::Quadric(o) => Box::new(o),
             DeserializableRTModel::Quadratic(o) => Box::new(o),
-            DeserializableRTModel::Quartic(o) => Box::new(o),
+            DeserializableRTModel::Quartic(o) => o.into_rt_model(),
+            DeserializableRTModel::Polynomial(o) => o.into_rt_model(),
+            DeserializableRTModel::Implicit(o) => o.into_rt_model(),
+            DeserializableRTModel::Torus(o) => o.into_rt_model(),
+            DeserializableRTModel::Mesh(o) => o.into_rt_model(context),
+            DeserializableRTModel::Sdf(o) => o.into_rt_model(),
+            DeserializableRTModel::Metaballs(o) => o.into_rt_model(),
+            DeserializableRTModel::Superellipsoid(o) => o.into_rt_model(),
+            DeserializableRTModel::Heightfield(o) => o.into_rt_model(context),
+            DeserializableRTModel::BezierPatch(o) => o.into_rt_model(context),
+            DeserializableRTModel::Lathe(o) => o.into_rt_model(),
+            DeserializableRTModel::Extrude(o) => o.into_rt_model(),
+            DeserializableRTModel::Disk(o) => o.into_rt_model(context),
+            DeserializableRTModel::Rectangle(o) => o.into_rt_model(context),
+            DeserializableRTModel::Triangle(o) => o.into_rt_model(context),
+        }
+    }
+
+    /// Distance field of the model, for the smooth CSG operators.
+    ///
+    /// Panics for models that have none, such as meshes and polynomial surfaces.
+    pub fn into_sdf<T: ImageLoader, M: MeshLoader>(
+        self,
+        context: &mut BuildContext<T, M>,
+    ) -> Box<dyn Sdf + Send + Sync> {
+        match self {
+            DeserializableRTModel::SmoothUnion(o) => o.into_sdf(SmoothOperator::Union, context),
+            DeserializableRTModel::SmoothIntersection(o) => {
+                o.into_sdf(SmoothOperator::Intersection, context)
+            }
+            DeserializableRTModel::SmoothDifference(o) => {
+                o.into_sdf(SmoothOperator::Difference, context)
+            }
+            DeserializableRTModel::Transform(o) => o.into_sdf(context),
+            DeserializableRTModel::Sphere(o) => o.into_sdf(),
+            DeserializableRTModel::Plane(o) => Box::new(o),
+            DeserializableRTModel::Cube(o) => Box::new(o),
+            DeserializableRTModel::Cylinder(o) => o.into_sdf(),
+            DeserializableRTModel::Cone(o) => o.into_sdf(),
+            DeserializableRTModel::Torus(o) => o.into_sdf(),
+            DeserializableRTModel::Sdf(o) => o.into_sdf(),
+            _ => panic!(
+                "Smooth CSG operands must have a distance field: use smoothUnion, \
+                 smoothIntersection, smoothDifference, transform, sphere, plane, cube, \
+                 cylinder, cone, torus or sdf"
+            ),
         }
     }
 }
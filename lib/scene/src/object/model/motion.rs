@@ -0,0 +1,262 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Mat4, Move, Vec3},
+    rt::Ray,
+};
+
+use crate::{
+    deserialize::{deserialize_move, deserialize_scale},
+    json_schema::{MoveSchema, Scale},
+    BuildContext, ImageLoader, MeshLoader,
+};
+
+use super::{
+    transform::{transformed_test, Rotation},
+    util::one,
+    DeserializableRTModel, Hit, RTModel,
+};
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct VelocityPath {
+    #[serde(deserialize_with = "deserialize_move")]
+    #[schemars(with = "MoveSchema")]
+    velocity: Move,
+}
+
+/// Placement of the model at one end of a linear path, scaled first, then rotated and translated.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Keyframe {
+    #[serde(default, deserialize_with = "deserialize_move")]
+    #[schemars(with = "MoveSchema")]
+    translation: Move,
+    #[serde(default)]
+    rotation: Option<Rotation>,
+    #[serde(default, deserialize_with = "deserialize_scale")]
+    scale: Scale,
+}
+
+/// Translations and scales are interpolated linearly, rotations along the shortest arc.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LinearPath {
+    #[serde(default)]
+    start: Keyframe,
+    end: Keyframe,
+    #[serde(default)]
+    start_time: f64,
+    #[serde(default = "one")]
+    end_time: f64,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "camelCase", deny_unknown_fields)]
+pub enum MotionPath {
+    Velocity(VelocityPath),
+    Linear(LinearPath),
+}
+
+#[derive(Clone, Copy)]
+struct Pose {
+    translation: Vec3,
+    /// Unit quaternion `[x, y, z, w]`
+    rotation: [f64; 4],
+    scale: Vec3,
+}
+
+impl Pose {
+    fn new(keyframe: &Keyframe) -> Pose {
+        Pose {
+            translation: *keyframe.translation,
+            rotation: keyframe
+                .rotation
+                .as_ref()
+                .map(Rotation::quaternion)
+                .unwrap_or([0.0, 0.0, 0.0, 1.0]),
+            scale: Vec3::new(keyframe.scale.x, keyframe.scale.y, keyframe.scale.z),
+        }
+    }
+
+    fn translation(translation: Vec3) -> Pose {
+        Pose {
+            translation,
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            scale: Vec3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    fn interpolate(&self, other: &Pose, t: f64) -> Pose {
+        let lerp = |a: Vec3, b: Vec3| a * (1.0 - t) + b * t;
+        Pose {
+            translation: lerp(self.translation, other.translation),
+            rotation: slerp(self.rotation, other.rotation, t),
+            scale: lerp(self.scale, other.scale),
+        }
+    }
+
+    fn matrix(&self) -> Mat4 {
+        let [x, y, z, w] = self.rotation;
+        Mat4::translation(self.translation) * Mat4::quaternion(x, y, z, w) * Mat4::scale(self.scale)
+    }
+
+    fn inverse(&self) -> Mat4 {
+        let [x, y, z, w] = self.rotation;
+        let scale = Vec3::new(1.0 / self.scale.x, 1.0 / self.scale.y, 1.0 / self.scale.z);
+        Mat4::scale(scale) * Mat4::quaternion(-x, -y, -z, w) * Mat4::translation(-self.translation)
+    }
+}
+
+/// Spherical interpolation between unit quaternions, along the shortest arc.
+fn slerp(a: [f64; 4], b: [f64; 4], t: f64) -> [f64; 4] {
+    let mut cos = (0..4).map(|i| a[i] * b[i]).sum::<f64>();
+    let b = if cos < 0.0 {
+        cos = -cos;
+        b.map(|c| -c)
+    } else {
+        b
+    };
+    let (wa, wb) = if cos > 0.9995 {
+        // Nearly the same rotation, where the angle is too small to divide by
+        (1.0 - t, t)
+    } else {
+        let angle = cos.acos();
+        let sin = angle.sin();
+        (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+    };
+    let q: [f64; 4] = std::array::from_fn(|i| wa * a[i] + wb * b[i]);
+    let length = q.iter().map(|c| c * c).sum::<f64>().sqrt();
+    q.map(|c| c / length)
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableMotion {
+    model: Box<DeserializableRTModel>,
+    path: MotionPath,
+}
+
+impl DeserializableMotion {
+    pub fn into_rt_model<T: ImageLoader, M: MeshLoader>(
+        self,
+        context: &mut BuildContext<T, M>,
+    ) -> Box<dyn RTModel + Send + Sync> {
+        let path = match self.path {
+            MotionPath::Velocity(path) => Path::Velocity(*path.velocity),
+            MotionPath::Linear(path) => {
+                let (start, end) = (Pose::new(&path.start), Pose::new(&path.end));
+                // Passing through zero in between would flatten the model and leave no inverse
+                let (a, b) = (start.scale, end.scale);
+                if a.x * b.x <= 0.0 || a.y * b.y <= 0.0 || a.z * b.z <= 0.0 {
+                    panic!("Motion keyframe scales must be nonzero and keep their signs");
+                }
+                Path::Linear {
+                    start,
+                    end,
+                    start_time: path.start_time,
+                    end_time: path.end_time,
+                }
+            }
+        };
+        Box::new(Motion {
+            model: self.model.into_rt_model(context),
+            path,
+            shutter: context.shutter,
+        })
+    }
+}
+
+enum Path {
+    Velocity(Vec3),
+    Linear {
+        start: Pose,
+        end: Pose,
+        start_time: f64,
+        end_time: f64,
+    },
+}
+
+impl Path {
+    fn pose(&self, time: f64) -> Pose {
+        match self {
+            Path::Velocity(velocity) => Pose::translation(*velocity * time),
+            Path::Linear {
+                start,
+                end,
+                start_time,
+                end_time,
+            } => {
+                let duration = end_time - start_time;
+                let t = if duration.abs() < 1e-12 {
+                    1.0
+                } else {
+                    ((time - start_time) / duration).clamp(0.0, 1.0)
+                };
+                start.interpolate(end, t)
+            }
+        }
+    }
+
+    fn rotates(&self) -> bool {
+        match self {
+            Path::Velocity(_) => false,
+            Path::Linear { start, end, .. } => start.rotation != end.rotation,
+        }
+    }
+}
+
+struct Motion {
+    model: Box<dyn RTModel + Send + Sync>,
+    path: Path,
+    shutter: (f64, f64),
+}
+
+impl RTModel for Motion {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        // Moving the ray backwards is the same as moving the model forwards
+        let inverse = self.path.pose(ray.time).inverse();
+        transformed_test(self.model.as_ref(), &inverse, &inverse.transpose(), ray)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let bounds = self.model.bounds()?;
+        if bounds.is_empty() {
+            return Some(bounds);
+        }
+        let (open, close) = self.shutter;
+        let (start, end) = (self.path.pose(open), self.path.pose(close));
+
+        if !self.path.rotates() {
+            // With a fixed rotation every point moves along a line as time goes on,
+            // so its places at the ends of the shutter interval enclose the sweep
+            let corners = |pose: Pose| {
+                let matrix = pose.matrix();
+                Aabb::from_points(
+                    bounds
+                        .corners()
+                        .map(|corner| matrix.transform_position(corner)),
+                )
+            };
+            return Some(corners(start).union(corners(end)));
+        }
+
+        // Turning in between, the model stays within a sphere around its origin
+        let radius = bounds
+            .corners()
+            .iter()
+            .map(|corner| corner.length())
+            .fold(0.0, f64::max);
+        let scale = [start.scale, end.scale]
+            .iter()
+            .flat_map(|scale| [scale.x.abs(), scale.y.abs(), scale.z.abs()])
+            .fold(0.0, f64::max);
+        let extent = Vec3::new(1.0, 1.0, 1.0) * (radius * scale);
+        Some(
+            Aabb::new(start.translation - extent, start.translation + extent).union(Aabb::new(
+                end.translation - extent,
+                end.translation + extent,
+            )),
+        )
+    }
+}
@@ -7,12 +7,13 @@ use super::{
     super::super::deserialize::{
         deserialize_direction, deserialize_ldr_color, deserialize_position,
     },
+    sdf::{Sdf, SdfMaterial},
     Hit, RTModel,
 };
 use schemars::JsonSchema;
 use serde::Deserialize;
 use seui_engine_raytracing_csg_renderer_core::types::{
-    math::{Direction, Position, Vec3},
+    math::{Aabb, Direction, Position, Vec3},
     rt::Ray,
 };
 use seui_engine_raytracing_csg_renderer_types::LDRColor;
@@ -114,4 +115,26 @@ impl RTModel for Plane {
 
         result
     }
+
+    fn bounds(&self) -> Option<Aabb> {
+        None
+    }
+}
+
+impl Sdf for Plane {
+    fn distance(&self, position: Vec3) -> f64 {
+        (position - *self.position).dot(*self.normal)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        None
+    }
+
+    fn material(&self, _position: Vec3) -> Option<SdfMaterial> {
+        Some(SdfMaterial {
+            albedo: self.albedo,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        })
+    }
 }
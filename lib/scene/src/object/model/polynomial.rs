@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Direction, Position, Vec3},
+    rt::Ray,
+};
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+use crate::{
+    deserialize::{
+        deserialize_ldr_color, deserialize_ldr_float, deserialize_optional_position,
+        deserialize_position,
+    },
+    json_schema::{LDRColorSchema, PositionSchema},
+};
+
+use super::{
+    expression::{self, Terms},
+    solver::{multiply, roots_in_range},
+    util::enhance_normal,
+    Hit, RTModel,
+};
+
+/// `coefficient * x^exponents[0] * y^exponents[1] * z^exponents[2]`.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Monomial {
+    exponents: [u32; 3],
+    coefficient: f64,
+}
+
+impl Monomial {
+    pub fn new(exponents: [u32; 3], coefficient: f64) -> Monomial {
+        Monomial {
+            exponents,
+            coefficient,
+        }
+    }
+}
+
+/// Ascending coefficients of the sum of `terms` along `origin + t direction`.
+pub fn restrict(terms: &[Monomial], origin: Vec3, direction: Vec3) -> Vec<f64> {
+    let degree = terms
+        .iter()
+        .map(|term| term.exponents.iter().sum::<u32>() as usize)
+        .max()
+        .unwrap_or(0);
+    let max_exponent = terms
+        .iter()
+        .flat_map(|term| term.exponents)
+        .max()
+        .unwrap_or(0) as usize;
+
+    // powers[axis][k] holds (origin + t direction)^k along that axis
+    let powers: Vec<Vec<Vec<f64>>> = (0..3)
+        .map(|axis| {
+            let linear = [origin[axis], direction[axis]];
+            let mut powers = vec![vec![1.0]];
+            for k in 1..=max_exponent {
+                powers.push(multiply(&powers[k - 1], &linear));
+            }
+            powers
+        })
+        .collect();
+
+    let mut coefficients = vec![0.0; degree + 1];
+    let mut xy = vec![0.0; degree + 1];
+    for term in terms {
+        let [x, y, z] = term.exponents.map(|e| e as usize);
+        // The product along x and y, then along z straight into the sum
+        xy[..=x + y].fill(0.0);
+        for (i, a) in powers[0][x].iter().enumerate() {
+            for (j, b) in powers[1][y].iter().enumerate() {
+                xy[i + j] += a * b;
+            }
+        }
+        for (i, a) in xy[..=x + y].iter().enumerate() {
+            for (k, b) in powers[2][z].iter().enumerate() {
+                coefficients[i + k] += term.coefficient * (a * b);
+            }
+        }
+    }
+    coefficients
+}
+
+/// Sum of `terms` at `position`.
+pub fn value(terms: &[Monomial], position: Vec3) -> f64 {
+    terms
+        .iter()
+        .map(|term| {
+            term.coefficient
+                * (0..3)
+                    .map(|axis| position[axis].powi(term.exponents[axis] as i32))
+                    .product::<f64>()
+        })
+        .sum()
+}
+
+/// Gradient of the sum of `terms` at `position`.
+pub fn gradient(terms: &[Monomial], position: Vec3) -> Vec3 {
+    let mut gradient = [0.0; 3];
+    for term in terms {
+        for (axis, g) in gradient.iter_mut().enumerate() {
+            let exponent = term.exponents[axis];
+            if exponent == 0 {
+                continue;
+            }
+            let mut value = term.coefficient * exponent as f64;
+            for other in 0..3 {
+                let e = term.exponents[other] - if other == axis { 1 } else { 0 };
+                value *= position[other].powi(e as i32);
+            }
+            *g += value;
+        }
+    }
+    Vec3::new(gradient[0], gradient[1], gradient[2])
+}
+
+/// Surface where the sum of `terms` and `equation` is zero, of any degree.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializablePolynomial {
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    #[serde(default, deserialize_with = "deserialize_ldr_color")]
+    #[schemars(with = "LDRColorSchema")]
+    albedo: LDRColor,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: f64,
+
+    #[serde(default)]
+    terms: Vec<Monomial>,
+    /// Polynomial in x, y and z like `(x^2 + y^2 + z^2 + R^2 - r^2)^2 - 4R^2(x^2 + y^2)`
+    #[serde(default)]
+    equation: Option<String>,
+    /// Values of the other names used in `equation`
+    #[serde(default)]
+    parameters: HashMap<String, f64>,
+
+    /// Point inside the solid, the points where the sum has its sign are inside.
+    /// When not given, the points where the sum is negative are inside
+    #[serde(default, deserialize_with = "deserialize_optional_position")]
+    #[schemars(with = "Option<PositionSchema>")]
+    inside: Option<Position>,
+}
+
+impl DeserializablePolynomial {
+    pub fn into_rt_model(self) -> Box<dyn RTModel + Send + Sync> {
+        let mut terms: Terms = Terms::new();
+        for term in self.terms {
+            *terms.entry(term.exponents).or_default() += term.coefficient;
+        }
+        if let Some(equation) = &self.equation {
+            let expanded = expression::parse(equation, &self.parameters)
+                .unwrap_or_else(|e| panic!("Invalid equation \"{}\": {}", equation, e))
+                .to_terms()
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Equation \"{}\" is not a polynomial, use an implicit model instead",
+                        equation
+                    )
+                });
+            for (exponents, coefficient) in expanded {
+                *terms.entry(exponents).or_default() += coefficient;
+            }
+        }
+
+        Box::new(Polynomial::new(
+            self.position,
+            terms,
+            self.inside,
+            self.albedo,
+            self.roughness,
+            self.metallic,
+        ))
+    }
+}
+
+pub struct Polynomial {
+    position: Position,
+    terms: Vec<Monomial>,
+    albedo: LDRColor,
+    roughness: f64,
+    metallic: f64,
+}
+
+impl Polynomial {
+    pub fn new(
+        position: Position,
+        terms: Terms,
+        inside: Option<Position>,
+        albedo: LDRColor,
+        roughness: f64,
+        metallic: f64,
+    ) -> Polynomial {
+        let mut polynomial = Polynomial {
+            position,
+            terms: terms
+                .into_iter()
+                .map(|(exponents, coefficient)| Monomial::new(exponents, coefficient))
+                .collect(),
+            albedo,
+            roughness,
+            metallic,
+        };
+        if let Some(inside) = inside {
+            if polynomial.value(*(inside - position)) > 0.0 {
+                for term in &mut polynomial.terms {
+                    term.coefficient = -term.coefficient;
+                }
+            }
+        }
+        polynomial
+    }
+
+    fn value(&self, position: Vec3) -> f64 {
+        value(&self.terms, position)
+    }
+
+    fn hit(&self, distance: f64, normal: Direction, is_front_face: bool) -> Hit {
+        Hit {
+            distance,
+            normal,
+            albedo: self.albedo,
+            is_front_face,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        }
+    }
+
+    /// Ascending distances where the ray crosses the surface.
+    fn roots(&self, origin: Vec3, direction: Vec3) -> Vec<f64> {
+        // Expand around the point closest to the model's origin, high degrees
+        // lose every digit of the leading coefficients far away from it
+        let closest = (-origin.dot(direction)).max(0.0);
+        let coefficients = restrict(&self.terms, origin + direction * closest, direction);
+        roots_in_range(&coefficients, -closest, f64::INFINITY)
+            .into_iter()
+            .map(|u| closest + u)
+            .collect()
+    }
+
+    fn normal(&self, position: Vec3) -> Direction {
+        Direction::new(gradient(&self.terms, position))
+    }
+}
+
+impl RTModel for Polynomial {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let origin = *(ray.origin - self.position);
+        let direction: Vec3 = *ray.direction;
+
+        let inside = self.value(origin) < 0.0;
+
+        let mut result = Vec::new();
+
+        let mut is_front_face = false;
+        if inside {
+            is_front_face = true;
+            result.push(self.hit(0.0, -ray.direction, true));
+        }
+        for distance in self.roots(origin, direction) {
+            is_front_face = !is_front_face;
+            let normal = self.normal(origin + direction * distance);
+            result.push(self.hit(
+                distance,
+                enhance_normal(ray.direction, normal, is_front_face),
+                is_front_face,
+            ));
+        }
+        if is_front_face {
+            result.push(self.hit(f64::INFINITY, ray.direction, false));
+        }
+
+        result
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        None
+    }
+}
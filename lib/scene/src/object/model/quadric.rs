@@ -1,18 +1,23 @@
 use schemars::JsonSchema;
 use serde::Deserialize;
 use seui_engine_raytracing_csg_renderer_core::types::{
-    math::{Direction, Position, Vec3},
+    math::{Aabb, Direction, Position, Vec3},
     rt::Ray,
 };
 use seui_engine_raytracing_csg_renderer_types::LDRColor;
 
 use crate::{
-    deserialize::{deserialize_ldr_color, deserialize_ldr_float, deserialize_position},
+    deserialize::{
+        deserialize_ldr_color, deserialize_ldr_float, deserialize_optional_position,
+        deserialize_position,
+    },
     json_schema::{LDRColorSchema, PositionSchema},
 };
 
 use super::{
-    util::{enhance_normal, zero},
+    clip::{clipped_test, ClipBounds},
+    solver::roots_in_range,
+    util::zero,
     Hit, RTModel,
 };
 
@@ -53,120 +58,78 @@ pub struct Quadric {
     #[serde(default = "zero")]
     c000: f64,
 
-    #[serde(default, deserialize_with = "deserialize_position")]
-    #[schemars(with = "PositionSchema")]
-    inside: Position,
+    /// Point inside the solid, when not given the points where the equation is negative are inside
+    #[serde(default, deserialize_with = "deserialize_optional_position")]
+    #[schemars(with = "Option<PositionSchema>")]
+    inside: Option<Position>,
+    /// Region the surface is cut down to, relative to `position`
+    #[serde(default)]
+    bounds: Option<ClipBounds>,
 }
 
 impl Quadric {
-    fn internal_test(&self, ray: Ray) -> Option<(Hit, Hit)> {
-        // Move the sphere to the origin for simplicity
+    /// Ascending coefficients along the ray, the first is the value at its origin.
+    fn coefficients(&self, ray: Ray) -> [f64; 3] {
         let origin: Position = (ray.origin - self.position).into();
 
-        let (a, b, c) = {
-            let mut a = 0.0;
-            let mut b = 0.0;
-            let mut c = 0.0;
-            // c200
-            a += self.c200 * ray.direction.x.powi(2);
-            b += self.c200 * 2.0 * ray.direction.x * origin.x;
-            c += self.c200 * origin.x.powi(2);
-            // c020
-            a += self.c020 * ray.direction.y.powi(2);
-            b += self.c020 * 2.0 * ray.direction.y * origin.y;
-            c += self.c020 * origin.y.powi(2);
-            // c002
-            a += self.c002 * ray.direction.z.powi(2);
-            b += self.c002 * 2.0 * ray.direction.z * origin.z;
-            c += self.c002 * origin.z.powi(2);
-            // c110
-            a += self.c110 * ray.direction.x * ray.direction.y;
-            b += self.c110 * ray.direction.x * origin.y;
-            b += self.c110 * origin.x * ray.direction.y;
-            c += self.c110 * origin.x * origin.y;
-            // c011
-            a += self.c011 * ray.direction.y * ray.direction.z;
-            b += self.c011 * ray.direction.y * origin.z;
-            b += self.c011 * origin.y * ray.direction.z;
-            c += self.c011 * origin.y * origin.z;
-            // c101
-            a += self.c101 * ray.direction.x * ray.direction.z;
-            b += self.c101 * ray.direction.x * origin.z;
-            b += self.c101 * origin.x * ray.direction.z;
-            c += self.c101 * origin.x * origin.z;
-            // c100
-            b += self.c100 * ray.direction.x;
-            c += self.c100 * origin.x;
-            // c010
-            b += self.c010 * ray.direction.y;
-            c += self.c010 * origin.y;
-            // c001
-            b += self.c001 * ray.direction.z;
-            c += self.c001 * origin.z;
-            // c000
-            c += self.c000;
-            // done
-            (a, b, c)
-        };
-
-        let discriminant = b.powi(2) - 4.0 * a * c;
-        if discriminant < 0.0 {
-            return None;
-        }
+        let mut a = 0.0;
+        let mut b = 0.0;
+        let mut c = 0.0;
+        // c200
+        a += self.c200 * ray.direction.x.powi(2);
+        b += self.c200 * 2.0 * ray.direction.x * origin.x;
+        c += self.c200 * origin.x.powi(2);
+        // c020
+        a += self.c020 * ray.direction.y.powi(2);
+        b += self.c020 * 2.0 * ray.direction.y * origin.y;
+        c += self.c020 * origin.y.powi(2);
+        // c002
+        a += self.c002 * ray.direction.z.powi(2);
+        b += self.c002 * 2.0 * ray.direction.z * origin.z;
+        c += self.c002 * origin.z.powi(2);
+        // c110
+        a += self.c110 * ray.direction.x * ray.direction.y;
+        b += self.c110 * ray.direction.x * origin.y;
+        b += self.c110 * origin.x * ray.direction.y;
+        c += self.c110 * origin.x * origin.y;
+        // c011
+        a += self.c011 * ray.direction.y * ray.direction.z;
+        b += self.c011 * ray.direction.y * origin.z;
+        b += self.c011 * origin.y * ray.direction.z;
+        c += self.c011 * origin.y * origin.z;
+        // c101
+        a += self.c101 * ray.direction.x * ray.direction.z;
+        b += self.c101 * ray.direction.x * origin.z;
+        b += self.c101 * origin.x * ray.direction.z;
+        c += self.c101 * origin.x * origin.z;
+        // c100
+        b += self.c100 * ray.direction.x;
+        c += self.c100 * origin.x;
+        // c010
+        b += self.c010 * ray.direction.y;
+        c += self.c010 * origin.y;
+        // c001
+        b += self.c001 * ray.direction.z;
+        c += self.c001 * origin.z;
+        // c000
+        c += self.c000;
+        [c, b, a]
+    }
 
-        let sqrt_d = discriminant.sqrt();
-        let (t1, t2) = {
-            let t1 = (-b - sqrt_d) / (2.0 * a);
-            let t2 = (-b + sqrt_d) / (2.0 * a);
-            if t1 < t2 {
-                (t1, t2)
-            } else {
-                (t2, t1)
-            }
-        };
-        if t2 < 0.0 {
-            return None;
-        }
+    fn internal_test(&self, ray: Ray, limit: f64) -> Vec<Hit> {
+        let origin: Position = (ray.origin - self.position).into();
 
-        if t1 < 0.0 {
-            Some((
-                Hit {
-                    distance: t2,
-                    normal: self.normal(origin + ray.direction * t2),
-                    albedo: self.albedo,
-                    is_front_face: true,
-                    roughness: self.roughness,
-                    metallic: self.metallic,
-                },
-                Hit {
-                    distance: f64::INFINITY,
-                    normal: ray.direction,
-                    albedo: self.albedo,
-                    is_front_face: false,
-                    roughness: self.roughness,
-                    metallic: self.metallic,
-                },
-            ))
-        } else {
-            Some((
-                Hit {
-                    distance: t1,
-                    normal: self.normal(origin + ray.direction * t1),
-                    albedo: self.albedo,
-                    is_front_face: true,
-                    roughness: self.roughness,
-                    metallic: self.metallic,
-                },
-                Hit {
-                    distance: t2,
-                    normal: self.normal(origin + ray.direction * t2),
-                    albedo: self.albedo,
-                    is_front_face: false,
-                    roughness: self.roughness,
-                    metallic: self.metallic,
-                },
-            ))
-        }
+        roots_in_range(&self.coefficients(ray), 0.0, limit)
+            .into_iter()
+            .map(|distance| Hit {
+                distance,
+                normal: self.normal(origin + ray.direction * distance),
+                albedo: self.albedo,
+                is_front_face: true, // decided later
+                roughness: self.roughness,
+                metallic: self.metallic,
+            })
+            .collect()
     }
 
     fn normal(&self, position: Position) -> Direction {
@@ -189,75 +152,27 @@ impl Quadric {
 
 impl RTModel for Quadric {
     fn test(&self, ray: Ray) -> Vec<Hit> {
-        let (inside_direction, inside_length) = (ray.origin - self.inside).direction_and_length();
-        let inside = if let Some((hit1, hit2)) = self.internal_test(Ray {
-            origin: self.inside,
-            direction: inside_direction,
-        }) {
-            (hit1.distance < inside_length) == (hit2.distance < inside_length)
-        } else {
-            true
-        };
-
-        let mut result = Vec::new();
-
-        if let Some((hit1, hit2)) = self.internal_test(ray) {
-            if inside {
-                result.push(Hit {
-                    distance: 0.0,
-                    normal: -ray.direction,
-                    albedo: self.albedo,
-                    is_front_face: true,
-                    roughness: self.roughness,
-                    metallic: self.metallic,
-                });
-                result.push(Hit {
-                    normal: enhance_normal(ray.direction, hit1.normal, false),
-                    is_front_face: false,
-                    ..hit1
-                });
-                result.push(Hit {
-                    normal: enhance_normal(ray.direction, hit2.normal, true),
-                    is_front_face: true,
-                    ..hit2
-                });
-                result.push(Hit {
-                    distance: f64::INFINITY,
-                    normal: ray.direction,
-                    albedo: self.albedo,
-                    is_front_face: false,
-                    roughness: self.roughness,
-                    metallic: self.metallic,
-                });
-            } else {
-                result.push(Hit {
-                    normal: enhance_normal(ray.direction, hit1.normal, true),
-                    ..hit1
-                });
-                result.push(Hit {
-                    normal: enhance_normal(ray.direction, hit2.normal, false),
-                    ..hit2
-                });
-            }
-        } else if inside {
-            result.push(Hit {
-                distance: 0.0,
-                normal: -ray.direction,
+        clipped_test(
+            &self.bounds,
+            self.position,
+            self.inside,
+            ray,
+            |ray, limit| self.internal_test(ray, limit),
+            |ray| self.coefficients(ray)[0] < 0.0,
+            |distance, normal, is_front_face| Hit {
+                distance,
+                normal,
                 albedo: self.albedo,
-                is_front_face: true,
+                is_front_face,
                 roughness: self.roughness,
                 metallic: self.metallic,
-            });
-            result.push(Hit {
-                distance: f64::INFINITY,
-                normal: ray.direction,
-                albedo: self.albedo,
-                is_front_face: false,
-                roughness: self.roughness,
-                metallic: self.metallic,
-            });
-        }
+            },
+        )
+    }
 
-        result
+    fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+            .as_ref()
+            .map(|bounds| bounds.aabb().translate(*self.position))
     }
 }
@@ -0,0 +1,239 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Direction, Vec3},
+    rt::Ray,
+};
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+use shape::DeserializableSdfShape;
+
+use crate::{
+    deserialize::{deserialize_ldr_color, deserialize_ldr_float},
+    json_schema::LDRColorSchema,
+};
+
+use super::{Hit, RTModel};
+
+pub mod shape;
+
+/// Marching stops once a step would be shorter than this, and crossings are refined to it
+const EPSILON: f64 = 1e-4;
+const MAX_STEPS: usize = 1000;
+/// How far rays are traced through unbounded fields
+const MAX_DISTANCE: f64 = 1e3;
+
+pub trait Sdf {
+    /// Signed distance to the surface, negative inside.
+    fn distance(&self, position: Vec3) -> f64;
+
+    /// Box enclosing the solid, `None` if it is unbounded.
+    fn bounds(&self) -> Option<Aabb>;
+
+    /// Upper bound on the gradient's length, which shortens the steps through distorted fields.
+    fn lipschitz(&self) -> f64 {
+        1.0
+    }
+
+    /// Material of the surface near `position`, `None` to use the one of the rendering model.
+    fn material(&self, _position: Vec3) -> Option<SdfMaterial> {
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SdfMaterial {
+    pub albedo: LDRColor,
+    pub roughness: f64,
+    pub metallic: f64,
+}
+
+impl SdfMaterial {
+    /// Linear blend, `self` at `t = 0` and `other` at `t = 1`.
+    pub fn mix(self, other: SdfMaterial, t: f64) -> SdfMaterial {
+        let mix = |a: f64, b: f64| a + (b - a) * t;
+        SdfMaterial {
+            albedo: LDRColor {
+                r: mix(self.albedo.r, other.albedo.r),
+                g: mix(self.albedo.g, other.albedo.g),
+                b: mix(self.albedo.b, other.albedo.b),
+            },
+            roughness: mix(self.roughness, other.roughness),
+            metallic: mix(self.metallic, other.metallic),
+        }
+    }
+}
+
+/// Gives a whole distance field a single material.
+struct Painted {
+    sdf: Box<dyn Sdf + Send + Sync>,
+    material: SdfMaterial,
+}
+
+impl Sdf for Painted {
+    fn distance(&self, position: Vec3) -> f64 {
+        self.sdf.distance(position)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.sdf.bounds()
+    }
+
+    fn lipschitz(&self) -> f64 {
+        self.sdf.lipschitz()
+    }
+
+    fn material(&self, _position: Vec3) -> Option<SdfMaterial> {
+        Some(self.material)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableSdf {
+    shape: DeserializableSdfShape,
+    #[serde(default, deserialize_with = "deserialize_ldr_color")]
+    #[schemars(with = "LDRColorSchema")]
+    albedo: LDRColor,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: f64,
+}
+
+impl DeserializableSdf {
+    pub fn into_rt_model(self) -> Box<dyn RTModel + Send + Sync> {
+        Box::new(SdfModel::new(self.into_sdf()))
+    }
+
+    pub fn into_sdf(self) -> Box<dyn Sdf + Send + Sync> {
+        Box::new(Painted {
+            sdf: self.shape.into_sdf(),
+            material: SdfMaterial {
+                albedo: self.albedo,
+                roughness: self.roughness,
+                metallic: self.metallic,
+            },
+        })
+    }
+}
+
+/// Renders a distance field by sphere tracing.
+pub struct SdfModel {
+    sdf: Box<dyn Sdf + Send + Sync>,
+    bounds: Option<Aabb>,
+    step_scale: f64,
+}
+
+impl SdfModel {
+    pub fn new(sdf: Box<dyn Sdf + Send + Sync>) -> SdfModel {
+        SdfModel {
+            bounds: sdf.bounds(),
+            step_scale: 1.0 / sdf.lipschitz(),
+            sdf,
+        }
+    }
+
+    fn hit(&self, distance: f64, position: Vec3, normal: Direction, is_front_face: bool) -> Hit {
+        let material = self.sdf.material(position).unwrap_or(SdfMaterial {
+            albedo: LDRColor::default(),
+            roughness: 0.0,
+            metallic: 0.0,
+        });
+        Hit {
+            distance,
+            normal,
+            albedo: material.albedo,
+            is_front_face,
+            roughness: material.roughness,
+            metallic: material.metallic,
+        }
+    }
+
+    /// Outward normal from the central difference gradient.
+    fn normal(&self, position: Vec3) -> Direction {
+        let h = EPSILON * 0.5;
+        let gradient = |axis: Vec3| {
+            self.sdf.distance(position + axis * h) - self.sdf.distance(position + axis * -h)
+        };
+        Direction::new(Vec3::new(
+            gradient(Vec3::X),
+            gradient(Vec3::Y),
+            gradient(Vec3::Z),
+        ))
+    }
+
+    /// Bisects a sign change of the field between `lo` (on the `inside` side) and `hi`.
+    fn refine(&self, origin: Vec3, direction: Vec3, mut lo: f64, mut hi: f64, inside: bool) -> f64 {
+        for _ in 0..30 {
+            let middle = 0.5 * (lo + hi);
+            if (self.sdf.distance(origin + direction * middle) < 0.0) == inside {
+                lo = middle;
+            } else {
+                hi = middle;
+            }
+        }
+        0.5 * (lo + hi)
+    }
+}
+
+impl RTModel for SdfModel {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let mut result = Vec::new();
+
+        let origin: Vec3 = *ray.origin;
+        let direction: Vec3 = *ray.direction;
+        let (mut t, t_end) = match &self.bounds {
+            Some(bounds) => {
+                let inverse_direction =
+                    Vec3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z);
+                let (enter, exit) = bounds.hit_range(origin, inverse_direction);
+                if enter > exit || exit < 0.0 {
+                    return result;
+                }
+                (enter.max(0.0), exit)
+            }
+            None => (0.0, MAX_DISTANCE),
+        };
+
+        let mut inside = self.sdf.distance(origin) < 0.0;
+        if inside {
+            result.push(self.hit(0.0, origin, -ray.direction, true));
+        }
+
+        let mut previous = t;
+        for _ in 0..MAX_STEPS {
+            if t > t_end {
+                break;
+            }
+            let distance = self.sdf.distance(origin + direction * t);
+            if (distance < 0.0) != inside {
+                let crossing = self.refine(origin, direction, previous, t, inside);
+                inside = !inside;
+                let position = origin + direction * crossing;
+                result.push(self.hit(crossing, position, self.normal(position), inside));
+                previous = t;
+                t += EPSILON;
+                continue;
+            }
+            previous = t;
+            t += (distance.abs() * self.step_scale).max(EPSILON);
+        }
+
+        if inside {
+            let exit = if self.bounds.is_some() {
+                t_end
+            } else {
+                f64::INFINITY
+            };
+            result.push(self.hit(exit, origin + direction * t_end, ray.direction, false));
+        }
+
+        result
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
+}
@@ -0,0 +1,473 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::math::{Aabb, Mat4, Move, Position, Vec3};
+
+use crate::{
+    deserialize::{
+        deserialize_move, deserialize_nonnegative_float, deserialize_position, deserialize_scale,
+    },
+    json_schema::{MoveSchema, PositionSchema, Scale},
+};
+
+use super::{
+    super::{transform::Rotation, util::one},
+    Sdf,
+};
+
+fn quarter() -> f64 {
+    0.25
+}
+
+fn cube(extent: Vec3) -> Aabb {
+    Aabb::new(-extent, extent)
+}
+
+fn abs(v: Vec3) -> Vec3 {
+    Vec3::new(v.x.abs(), v.y.abs(), v.z.abs())
+}
+
+fn positive(v: Vec3) -> Vec3 {
+    Vec3::new(v.x.max(0.0), v.y.max(0.0), v.z.max(0.0))
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SdfSphere {
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    radius: f64,
+}
+
+/// Box with optionally rounded edges, which stay within its extents.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SdfBox {
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    /// Half of the size along each axis
+    #[serde(default, deserialize_with = "deserialize_scale")]
+    scale: Scale,
+    #[serde(default, deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    radius: f64,
+}
+
+/// Torus around the Z axis.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SdfTorus {
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    major_radius: f64,
+    #[serde(
+        default = "quarter",
+        deserialize_with = "deserialize_nonnegative_float"
+    )]
+    #[schemars(range(min = 0))]
+    minor_radius: f64,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SdfCapsule {
+    #[serde(deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    start: Position,
+    #[serde(deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    end: Position,
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    radius: f64,
+}
+
+/// Capped cylinder along the Z axis.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SdfCylinder {
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    radius: f64,
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    height: f64,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SdfTranslate {
+    shape: Box<DeserializableSdfShape>,
+    #[serde(deserialize_with = "deserialize_move")]
+    #[schemars(with = "MoveSchema")]
+    offset: Move,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SdfRotate {
+    shape: Box<DeserializableSdfShape>,
+    rotation: Rotation,
+}
+
+/// Uniform scale, the only one keeping the field a true distance.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SdfScale {
+    shape: Box<DeserializableSdfShape>,
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    factor: f64,
+}
+
+/// Twists the shape around the Z axis, which must be bounded.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SdfTwist {
+    shape: Box<DeserializableSdfShape>,
+    /// Degrees of rotation per unit along the Z axis
+    rate: f64,
+}
+
+/// Repeats the shape on a grid, which must fit within a cell of the grid.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SdfRepeat {
+    shape: Box<DeserializableSdfShape>,
+    /// Spacing along each axis, 0 to not repeat along it
+    #[serde(deserialize_with = "deserialize_scale")]
+    period: Scale,
+    /// Copies on each side of the original along each axis, unlimited if omitted
+    #[serde(default)]
+    limit: Option<[u32; 3]>,
+}
+
+/// Inflates the shape, rounding its edges.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SdfRound {
+    shape: Box<DeserializableSdfShape>,
+    #[serde(deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    radius: f64,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "camelCase", deny_unknown_fields)]
+pub enum DeserializableSdfShape {
+    Sphere(SdfSphere),
+    Box(SdfBox),
+    Torus(SdfTorus),
+    Capsule(SdfCapsule),
+    Cylinder(SdfCylinder),
+    Translate(SdfTranslate),
+    Rotate(SdfRotate),
+    Scale(SdfScale),
+    Twist(SdfTwist),
+    Repeat(SdfRepeat),
+    Round(SdfRound),
+}
+
+impl DeserializableSdfShape {
+    pub fn into_sdf(self) -> Box<dyn Sdf + Send + Sync> {
+        match self {
+            DeserializableSdfShape::Sphere(s) => Box::new(s),
+            DeserializableSdfShape::Box(s) => Box::new(s),
+            DeserializableSdfShape::Torus(s) => Box::new(s),
+            DeserializableSdfShape::Capsule(s) => Box::new(s),
+            DeserializableSdfShape::Cylinder(s) => Box::new(s),
+            DeserializableSdfShape::Translate(s) => Box::new(Translate {
+                shape: s.shape.into_sdf(),
+                offset: *s.offset,
+            }),
+            DeserializableSdfShape::Rotate(s) => {
+                let matrix = s.rotation.matrix();
+                Box::new(Rotate {
+                    shape: s.shape.into_sdf(),
+                    matrix,
+                    inverse: matrix.transpose(),
+                })
+            }
+            DeserializableSdfShape::Scale(s) => Box::new(UniformScale {
+                shape: s.shape.into_sdf(),
+                factor: s.factor.max(1e-9),
+            }),
+            DeserializableSdfShape::Twist(s) => {
+                let shape = s.shape.into_sdf();
+                let bounds = shape
+                    .bounds()
+                    .expect("A twisted shape must be bounded, give repeats a limit");
+                let radius = bounds
+                    .corners()
+                    .iter()
+                    .fold(0.0f64, |r, c| r.max((c.x * c.x + c.y * c.y).sqrt()));
+                Box::new(Twist {
+                    shape,
+                    rate: s.rate.to_radians(),
+                    radius,
+                })
+            }
+            DeserializableSdfShape::Repeat(s) => Box::new(Repeat {
+                shape: s.shape.into_sdf(),
+                period: Vec3::new(s.period.x, s.period.y, s.period.z),
+                limit: s
+                    .limit
+                    .map(|l| Vec3::new(l[0] as f64, l[1] as f64, l[2] as f64)),
+            }),
+            DeserializableSdfShape::Round(s) => Box::new(Round {
+                shape: s.shape.into_sdf(),
+                radius: s.radius,
+            }),
+        }
+    }
+}
+
+impl Sdf for SdfSphere {
+    fn distance(&self, position: Vec3) -> f64 {
+        (position - *self.position).length() - self.radius
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(cube(Vec3::new(self.radius, self.radius, self.radius)).translate(*self.position))
+    }
+}
+
+impl Sdf for SdfBox {
+    fn distance(&self, position: Vec3) -> f64 {
+        let radius = self
+            .radius
+            .min(self.scale.x)
+            .min(self.scale.y)
+            .min(self.scale.z);
+        let extent = Vec3::new(self.scale.x, self.scale.y, self.scale.z);
+        let q = abs(position - *self.position) - (extent - Vec3::new(radius, radius, radius));
+        positive(q).length() + q.x.max(q.y).max(q.z).min(0.0) - radius
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(cube(Vec3::new(self.scale.x, self.scale.y, self.scale.z)).translate(*self.position))
+    }
+}
+
+impl Sdf for SdfTorus {
+    fn distance(&self, position: Vec3) -> f64 {
+        let p = position - *self.position;
+        let ring = (p.x * p.x + p.y * p.y).sqrt() - self.major_radius;
+        (ring * ring + p.z * p.z).sqrt() - self.minor_radius
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let radius = self.major_radius + self.minor_radius;
+        Some(cube(Vec3::new(radius, radius, self.minor_radius)).translate(*self.position))
+    }
+}
+
+impl Sdf for SdfCapsule {
+    fn distance(&self, position: Vec3) -> f64 {
+        let p = position - *self.start;
+        let axis = *self.end - *self.start;
+        let length_square = axis.length_square();
+        let h = if length_square > 0.0 {
+            (p.dot(axis) / length_square).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        (p - axis * h).length() - self.radius
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        Some(
+            Aabb::from_points([*self.start, *self.end])
+                .union(cube(extent).translate(*self.start))
+                .union(cube(extent).translate(*self.end)),
+        )
+    }
+}
+
+impl Sdf for SdfCylinder {
+    fn distance(&self, position: Vec3) -> f64 {
+        let p = position - *self.position;
+        let dx = (p.x * p.x + p.y * p.y).sqrt() - self.radius;
+        let dy = p.z.abs() - self.height / 2.0;
+        dx.max(dy).min(0.0) + (dx.max(0.0).powi(2) + dy.max(0.0).powi(2)).sqrt()
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(cube(Vec3::new(self.radius, self.radius, self.height / 2.0)).translate(*self.position))
+    }
+}
+
+struct Translate {
+    shape: Box<dyn Sdf + Send + Sync>,
+    offset: Vec3,
+}
+
+impl Sdf for Translate {
+    fn distance(&self, position: Vec3) -> f64 {
+        self.shape.distance(position - self.offset)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(self.shape.bounds()?.translate(self.offset))
+    }
+
+    fn lipschitz(&self) -> f64 {
+        self.shape.lipschitz()
+    }
+}
+
+struct Rotate {
+    shape: Box<dyn Sdf + Send + Sync>,
+    matrix: Mat4,
+    inverse: Mat4,
+}
+
+impl Sdf for Rotate {
+    fn distance(&self, position: Vec3) -> f64 {
+        self.shape.distance(self.inverse.transform_vector(position))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::from_points(
+            self.shape
+                .bounds()?
+                .corners()
+                .map(|corner| self.matrix.transform_vector(corner)),
+        ))
+    }
+
+    fn lipschitz(&self) -> f64 {
+        self.shape.lipschitz()
+    }
+}
+
+struct UniformScale {
+    shape: Box<dyn Sdf + Send + Sync>,
+    factor: f64,
+}
+
+impl Sdf for UniformScale {
+    fn distance(&self, position: Vec3) -> f64 {
+        self.shape.distance(position * (1.0 / self.factor)) * self.factor
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let bounds = self.shape.bounds()?;
+        Some(Aabb::new(
+            bounds.min * self.factor,
+            bounds.max * self.factor,
+        ))
+    }
+
+    fn lipschitz(&self) -> f64 {
+        self.shape.lipschitz()
+    }
+}
+
+struct Twist {
+    shape: Box<dyn Sdf + Send + Sync>,
+    rate: f64,
+    /// Distance from the axis to the farthest corner of the shape's bounds
+    radius: f64,
+}
+
+impl Sdf for Twist {
+    fn distance(&self, position: Vec3) -> f64 {
+        let (sin, cos) = (-self.rate * position.z).sin_cos();
+        self.shape.distance(Vec3::new(
+            cos * position.x - sin * position.y,
+            sin * position.x + cos * position.y,
+            position.z,
+        ))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let bounds = self.shape.bounds()?;
+        let radius = self.radius;
+        Some(Aabb::new(
+            Vec3::new(-radius, -radius, bounds.min.z),
+            Vec3::new(radius, radius, bounds.max.z),
+        ))
+    }
+
+    /// Twisting stretches the field by up to `sqrt(1 + (rate * r)^2)` at distance `r` from the axis.
+    fn lipschitz(&self) -> f64 {
+        self.shape.lipschitz() * (1.0 + (self.rate * self.radius).powi(2)).sqrt()
+    }
+}
+
+struct Repeat {
+    shape: Box<dyn Sdf + Send + Sync>,
+    period: Vec3,
+    limit: Option<Vec3>,
+}
+
+impl Sdf for Repeat {
+    fn distance(&self, position: Vec3) -> f64 {
+        let repeat = |p: f64, period: f64, limit: Option<f64>| {
+            if period <= 0.0 {
+                return p;
+            }
+            let cell = (p / period).round();
+            let cell = limit.map_or(cell, |limit| cell.clamp(-limit, limit));
+            p - period * cell
+        };
+        self.shape.distance(Vec3::new(
+            repeat(position.x, self.period.x, self.limit.map(|l| l.x)),
+            repeat(position.y, self.period.y, self.limit.map(|l| l.y)),
+            repeat(position.z, self.period.z, self.limit.map(|l| l.z)),
+        ))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let bounds = self.shape.bounds()?;
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None if self.period.x > 0.0 || self.period.y > 0.0 || self.period.z > 0.0 => {
+                return None
+            }
+            None => return Some(bounds),
+        };
+        let reach = Vec3::new(
+            self.period.x * limit.x,
+            self.period.y * limit.y,
+            self.period.z * limit.z,
+        );
+        Some(Aabb::new(bounds.min - reach, bounds.max + reach))
+    }
+
+    fn lipschitz(&self) -> f64 {
+        self.shape.lipschitz()
+    }
+}
+
+struct Round {
+    shape: Box<dyn Sdf + Send + Sync>,
+    radius: f64,
+}
+
+impl Sdf for Round {
+    fn distance(&self, position: Vec3) -> f64 {
+        self.shape.distance(position) - self.radius
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let bounds = self.shape.bounds()?;
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(bounds.min - extent, bounds.max + extent))
+    }
+
+    fn lipschitz(&self) -> f64 {
+        self.shape.lipschitz()
+    }
+}
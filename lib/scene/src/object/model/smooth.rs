@@ -0,0 +1,119 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::math::{Aabb, Vec3};
+
+use crate::{deserialize::deserialize_nonnegative_float, BuildContext, ImageLoader, MeshLoader};
+
+use super::{
+    sdf::{Sdf, SdfMaterial, SdfModel},
+    DeserializableRTModel, RTModel,
+};
+
+/// Both operands must have a distance field, see [`DeserializableRTModel::into_sdf`].
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableSmoothOperation {
+    a: Box<DeserializableRTModel>,
+    b: Box<DeserializableRTModel>,
+    /// Size of the blend between the operands
+    #[serde(deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    radius: f64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum SmoothOperator {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl DeserializableSmoothOperation {
+    pub fn into_rt_model<T: ImageLoader, M: MeshLoader>(
+        self,
+        operator: SmoothOperator,
+        context: &mut BuildContext<T, M>,
+    ) -> Box<dyn RTModel + Send + Sync> {
+        Box::new(SdfModel::new(self.into_sdf(operator, context)))
+    }
+
+    pub fn into_sdf<T: ImageLoader, M: MeshLoader>(
+        self,
+        operator: SmoothOperator,
+        context: &mut BuildContext<T, M>,
+    ) -> Box<dyn Sdf + Send + Sync> {
+        Box::new(SmoothOperation {
+            a: self.a.into_sdf(context),
+            b: self.b.into_sdf(context),
+            radius: self.radius,
+            operator,
+        })
+    }
+}
+
+struct SmoothOperation {
+    a: Box<dyn Sdf + Send + Sync>,
+    b: Box<dyn Sdf + Send + Sync>,
+    radius: f64,
+    operator: SmoothOperator,
+}
+
+impl SmoothOperation {
+    /// Blended distance and the weight of `b` in it, using the polynomial smooth minimum.
+    fn blend(&self, position: Vec3) -> (f64, f64) {
+        let a = self.a.distance(position);
+        let b = self.b.distance(position);
+        let k = self.radius;
+
+        let (a, b, sign) = match self.operator {
+            SmoothOperator::Union => (a, b, 1.0),
+            SmoothOperator::Intersection => (-a, -b, -1.0),
+            SmoothOperator::Difference => (-a, b, -1.0),
+        };
+        // Smooth minimum of a and b, negated back for the maximum based operators
+        if k <= 0.0 {
+            return if a < b {
+                (sign * a, 0.0)
+            } else {
+                (sign * b, 1.0)
+            };
+        }
+        let h = (0.5 + 0.5 * (a - b) / k).clamp(0.0, 1.0);
+        let distance = a + (b - a) * h - k * h * (1.0 - h);
+        (sign * distance, h)
+    }
+}
+
+impl Sdf for SmoothOperation {
+    fn distance(&self, position: Vec3) -> f64 {
+        self.blend(position).0
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        match self.operator {
+            // The blend bulges out by at most a quarter of the radius
+            SmoothOperator::Union => {
+                let bounds = self.a.bounds()?.union(self.b.bounds()?);
+                let extent = Vec3::new(self.radius, self.radius, self.radius) * 0.25;
+                Some(Aabb::new(bounds.min - extent, bounds.max + extent))
+            }
+            SmoothOperator::Intersection => match (self.a.bounds(), self.b.bounds()) {
+                (Some(a), Some(b)) => Some(a.intersection(b)),
+                (a, b) => a.or(b),
+            },
+            SmoothOperator::Difference => self.a.bounds(),
+        }
+    }
+
+    fn lipschitz(&self) -> f64 {
+        self.a.lipschitz().max(self.b.lipschitz())
+    }
+
+    fn material(&self, position: Vec3) -> Option<SdfMaterial> {
+        let weight = self.blend(position).1;
+        match (self.a.material(position), self.b.material(position)) {
+            (Some(a), Some(b)) => Some(a.mix(b, weight)),
+            (a, b) => a.or(b),
+        }
+    }
+}
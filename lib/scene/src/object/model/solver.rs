@@ -0,0 +1,463 @@
+//! Real roots of polynomials in one variable, given by their ascending coefficients.
+
+/// Roots further away than this are beyond any scene and not searched for.
+const MAX_ROOT: f64 = 1e6;
+/// Leading coefficients this small relative to the largest term over the range are dropped.
+const NEGLIGIBLE: f64 = 1e-12;
+/// Values within this many rounding errors of zero count as zero.
+const ROUNDING_ERRORS: f64 = 64.0;
+
+/// Evaluates `c[0] + c[1] t + c[2] t^2 + ...` and its derivative.
+pub fn evaluate(coefficients: &[f64], t: f64) -> (f64, f64) {
+    let mut value = 0.0;
+    let mut derivative = 0.0;
+    for &c in coefficients.iter().rev() {
+        derivative = derivative * t + value;
+        value = value * t + c;
+    }
+    (value, derivative)
+}
+
+/// Bound on the error of [`evaluate`], from the size of the terms summed at `t`.
+fn rounding_error(coefficients: &[f64], t: f64) -> f64 {
+    let magnitude = coefficients
+        .iter()
+        .rev()
+        .fold(0.0, |sum, c| sum * t.abs() + c.abs());
+    ROUNDING_ERRORS * f64::EPSILON * magnitude
+}
+
+/// Product of two polynomials.
+pub fn multiply(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut result = vec![0.0; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            result[i + j] += x * y;
+        }
+    }
+    result
+}
+
+/// Root in `[lo, hi]` where the polynomial changes sign, by Newton steps kept inside the bracket.
+fn bracketed_root(coefficients: &[f64], mut lo: f64, mut hi: f64) -> f64 {
+    let lo_is_negative = evaluate(coefficients, lo).0 < 0.0;
+    let mut t = 0.5 * (lo + hi);
+    for _ in 0..100 {
+        let (value, derivative) = evaluate(coefficients, t);
+        if value == 0.0 {
+            break;
+        }
+        if (value < 0.0) == lo_is_negative {
+            lo = t;
+        } else {
+            hi = t;
+        }
+
+        let newton = t - value / derivative;
+        let next = if newton > lo && newton < hi {
+            newton
+        } else {
+            0.5 * (lo + hi)
+        };
+        if (next - t).abs() <= f64::EPSILON * t.abs() || next == lo || next == hi {
+            t = next;
+            break;
+        }
+        t = next;
+    }
+    t
+}
+
+/// Ascending real roots in `[lo, hi]`, which may be infinite.
+///
+/// Simple roots are found where the sign changes. Tangent roots, where the polynomial only
+/// touches zero, are reported twice, so that counting roots still tells inside from outside.
+pub fn roots_in_range(coefficients: &[f64], lo: f64, hi: f64) -> Vec<f64> {
+    let degree = match coefficients.iter().rposition(|&c| c != 0.0) {
+        Some(degree) if degree > 0 => degree,
+        _ => return Vec::new(),
+    };
+    let coefficients = &coefficients[..=degree];
+
+    // Cauchy's bound, no root lies further than this
+    let leading = coefficients[degree];
+    let bound = 1.0
+        + coefficients[..degree]
+            .iter()
+            .map(|c| (c / leading).abs())
+            .fold(0.0, f64::max);
+    let reach = bound.min(MAX_ROOT);
+    let (lo, hi) = (lo.max(-reach), hi.min(reach));
+    if lo > hi {
+        return Vec::new();
+    }
+
+    // Measure the variable in units of the range, so that the tolerances
+    // compare every term by its largest value over the range
+    let scale = lo.abs().max(hi.abs()).max(f64::MIN_POSITIVE);
+    let mut power = 1.0;
+    let scaled: Vec<f64> = coefficients
+        .iter()
+        .map(|c| {
+            let scaled = c * power;
+            power *= scale;
+            scaled
+        })
+        .collect();
+    isolate(&scaled, lo / scale, hi / scale)
+        .into_iter()
+        .map(|u| u * scale)
+        .collect()
+}
+
+/// The roots of the derivative split the range into monotonic pieces,
+/// each holding at most one simple root that can be safely bracketed.
+fn isolate(coefficients: &[f64], lo: f64, hi: f64) -> Vec<f64> {
+    let largest = coefficients.iter().fold(0.0f64, |m, c| m.max(c.abs()));
+    let degree = match coefficients
+        .iter()
+        .rposition(|c| c.abs() > largest * NEGLIGIBLE)
+    {
+        Some(degree) if degree > 0 => degree,
+        _ => return Vec::new(),
+    };
+    let coefficients = &coefficients[..=degree];
+
+    if degree == 1 {
+        let root = -coefficients[0] / coefficients[1];
+        return if (lo..=hi).contains(&root) {
+            vec![root]
+        } else {
+            Vec::new()
+        };
+    }
+
+    let derivative: Vec<f64> = coefficients
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, c)| c * i as f64)
+        .collect();
+    let mut points = vec![lo];
+    points.extend(isolate(&derivative, lo, hi));
+    points.push(hi);
+    points.dedup();
+
+    let values: Vec<f64> = points
+        .iter()
+        .map(|&t| {
+            let value = evaluate(coefficients, t).0;
+            if value.abs() <= rounding_error(coefficients, t) {
+                0.0
+            } else {
+                value
+            }
+        })
+        .collect();
+
+    // Whether the polynomial rises over each monotonic piece, from the slope in its middle,
+    // which unlike the values at its ends can't be rounded to zero
+    let rises: Vec<bool> = points
+        .windows(2)
+        .map(|piece| evaluate(coefficients, 0.5 * (piece[0] + piece[1])).1 > 0.0)
+        .collect();
+
+    let mut roots = Vec::new();
+    let mut i = 0;
+    while i < points.len() {
+        if values[i] != 0.0 {
+            if i + 1 < points.len()
+                && values[i + 1] != 0.0
+                && (values[i] < 0.0) != (values[i + 1] < 0.0)
+            {
+                roots.push(bracketed_root(coefficients, points[i], points[i + 1]));
+            }
+            i += 1;
+            continue;
+        }
+
+        // A run of points within rounding of zero
+        let (start, first) = (i, roots.len());
+        while i < points.len() && values[i] == 0.0 {
+            roots.push(points[i]);
+            // An extremum touching zero, the ray grazes the surface. Near zero it may as well
+            // dip across, which gives two roots just the same
+            let is_interior = i > 0 && i + 1 < points.len();
+            if is_interior && rises[i - 1] != rises[i] {
+                roots.push(points[i]);
+            }
+            i += 1;
+        }
+        // Between two known signs the count must still tell whether the sign changes,
+        // as a simple root next to the run is lost in it
+        if start > 0 && i < points.len() {
+            let changes_sign = (values[start - 1] < 0.0) != (values[i] < 0.0);
+            if (roots.len() - first) % 2 == 1 && !changes_sign
+                || (roots.len() - first) % 2 == 0 && changes_sign
+            {
+                if roots.len() - first >= 2 && roots[first] == roots[first + 1] {
+                    roots.remove(first);
+                } else {
+                    roots.insert(first, points[start]);
+                }
+            }
+        }
+    }
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use seui_engine_raytracing_csg_renderer_core::types::{
+        math::{Direction, Position, Vec3},
+        rt::Ray,
+    };
+
+    use super::{evaluate, multiply, roots_in_range, rounding_error};
+    use crate::object::model::{
+        quadratic::Quadratic, quadric::Quadric, quartic::DeserializableQuartic, RTModel,
+    };
+
+    /// Ascending coefficients of the product of `(t - root)` over `roots`.
+    fn from_roots(roots: &[f64]) -> Vec<f64> {
+        roots
+            .iter()
+            .fold(vec![1.0], |product, root| multiply(&product, &[-root, 1.0]))
+    }
+
+    fn assert_roots(found: &[f64], expected: &[f64]) {
+        assert_eq!(found.len(), expected.len(), "{found:?} != {expected:?}");
+        for (found, expected) in found.iter().zip(expected) {
+            assert!(
+                (found - expected).abs() <= 1e-9 * expected.abs().max(1.0),
+                "{found} != {expected}"
+            );
+        }
+    }
+
+    fn assert_distances(model: &dyn RTModel, ray: Ray, expected: &[f64]) {
+        let hits = model.test(ray);
+        let distances: Vec<f64> = hits.iter().map(|hit| hit.distance).collect();
+        assert_roots(&distances, expected);
+        for (i, hit) in hits.iter().enumerate() {
+            assert_eq!(hit.is_front_face, i % 2 == 0);
+        }
+    }
+
+    fn ray(origin: [f64; 3], direction: [f64; 3]) -> Ray {
+        Ray {
+            origin: Position::new(Vec3::new(origin[0], origin[1], origin[2])),
+            direction: Direction::new(Vec3::new(direction[0], direction[1], direction[2])),
+            time: 0.0,
+        }
+    }
+
+    #[test]
+    fn finds_simple_roots() {
+        for roots in [
+            vec![1.5],
+            vec![-2.0, 3.0],
+            vec![0.25, 1.0, 4.0],
+            vec![0.5, 2.0, 40.0, 900.0],
+            vec![1e-3, 1.0, 1e3],
+            vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6],
+        ] {
+            let lo = roots[0] - 1.0;
+            let hi = roots[roots.len() - 1] + 1.0;
+            assert_roots(&roots_in_range(&from_roots(&roots), lo, hi), &roots);
+        }
+    }
+
+    #[test]
+    fn reports_double_roots_twice() {
+        let roots = [2.0, 2.0, 5.0];
+        assert_roots(&roots_in_range(&from_roots(&roots), 0.0, 10.0), &roots);
+        let roots = [-1.0, 3.0, 3.0, 4.0];
+        assert_roots(&roots_in_range(&from_roots(&roots), 0.0, 10.0), &roots[1..]);
+        // A ray grazing the unit sphere at t = 5: t^2 - 10 t + 25
+        assert_roots(&roots_in_range(&[25.0, -10.0, 1.0], 0.0, 10.0), &[5.0, 5.0]);
+    }
+
+    /// Deterministic values in `[0, 1)`, so that failures can be reproduced.
+    fn sampler(mut state: u64) -> impl FnMut() -> f64 {
+        move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        }
+    }
+
+    /// Up to six roots in `[-20, 20]`, some clustered within `spread` of an earlier one and
+    /// some repeated once, distinct ones at least `gap` apart.
+    fn random_roots(random: &mut impl FnMut() -> f64, spread: f64, gap: f64) -> Vec<f64> {
+        let degree = 1 + (random() * 6.0) as usize;
+        let mut roots: Vec<f64> = Vec::new();
+        while roots.len() < degree {
+            let kind = random();
+            let earlier = roots.get((random() * roots.len() as f64) as usize).copied();
+            let root = match earlier {
+                Some(double)
+                    if kind < 0.25 && roots.iter().filter(|&&r| r == double).count() == 1 =>
+                {
+                    roots.push(double);
+                    continue;
+                }
+                Some(near) if kind < 0.5 => near + spread * (2.0 * random() - 1.0),
+                _ => 40.0 * random() - 20.0,
+            };
+            if roots.iter().all(|r| (r - root).abs() >= gap) {
+                roots.push(root);
+            }
+        }
+        roots.sort_by(f64::total_cmp);
+        roots
+    }
+
+    #[test]
+    fn recovers_random_roots() {
+        let mut random = sampler(0x5eed);
+        for _ in 0..2000 {
+            let roots = random_roots(&mut random, 1.0, 0.3);
+            let (lo, hi) = (roots[0] - 1.0, roots[roots.len() - 1] + 1.0);
+            let coefficients = from_roots(&roots);
+            let found = roots_in_range(&coefficients, lo, hi);
+            assert_eq!(found.len(), roots.len(), "{found:?} != {roots:?}");
+            for (found, &expected) in found.iter().zip(&roots) {
+                // Evaluating near a root is only good to the rounding of the largest term, which
+                // moves a simple root by that over the slope, a double one by its square root
+                let noise = 64.0
+                    * f64::EPSILON
+                    * coefficients
+                        .iter()
+                        .rev()
+                        .fold(0.0, |sum, c| sum * expected.abs() + c.abs());
+                let others: f64 = roots
+                    .iter()
+                    .filter(|&&root| root != expected)
+                    .map(|root| (root - expected).abs())
+                    .product();
+                let shift = if roots.iter().filter(|&&root| root == expected).count() == 2 {
+                    (noise / others).sqrt()
+                } else {
+                    noise / others
+                };
+                assert!(
+                    (found - expected).abs() <= shift + 1e-12 * expected.abs().max(1.0),
+                    "{found} != {expected} in {roots:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_parity_of_tight_clusters() {
+        let mut random = sampler(0xc1057e5);
+        for _ in 0..2000 {
+            // Roots this close are within rounding of touching, so only their parity is known
+            let roots = random_roots(&mut random, 0.02, 1e-3);
+            let (lo, hi) = (roots[0] - 1.0, roots[roots.len() - 1] + 1.0);
+            let coefficients = from_roots(&roots);
+            let found = roots_in_range(&coefficients, lo, hi);
+            assert_eq!(found.len() % 2, roots.len() % 2, "{found:?} != {roots:?}");
+            for &found in &found {
+                // Anywhere the polynomial is zero to within rounding
+                assert!(
+                    evaluate(&coefficients, found).0.abs()
+                        <= 2.0 * rounding_error(&coefficients, found),
+                    "{found} is no root of {roots:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn clips_to_range() {
+        let coefficients = from_roots(&[-1.0, 0.5, 2.0, 7.0]);
+        assert_roots(&roots_in_range(&coefficients, 0.0, 3.0), &[0.5, 2.0]);
+        assert_roots(&roots_in_range(&coefficients, 1.0, 1.5), &[]);
+        assert_roots(&roots_in_range(&coefficients, -5.0, 0.0), &[-1.0]);
+    }
+
+    #[test]
+    fn searches_unbounded_range() {
+        let roots = [1.0, 100.0, 10000.0];
+        assert_roots(
+            &roots_in_range(&from_roots(&roots), 0.0, f64::INFINITY),
+            &roots,
+        );
+        assert_roots(
+            &roots_in_range(&from_roots(&[-3.0, 8.0]), 0.0, f64::INFINITY),
+            &[8.0],
+        );
+    }
+
+    #[test]
+    fn ignores_zero_leading_coefficients() {
+        assert_roots(&roots_in_range(&[-2.0, 1.0, 0.0], 0.0, 10.0), &[2.0]);
+        assert_roots(
+            &roots_in_range(&[2.0, -3.0, 1.0, 0.0, 0.0], 0.0, 10.0),
+            &[1.0, 2.0],
+        );
+        assert_roots(&roots_in_range(&[1.0, 0.0, 0.0], 0.0, 10.0), &[]);
+        assert_roots(&roots_in_range(&[0.0, 0.0], 0.0, 10.0), &[]);
+    }
+
+    #[test]
+    fn hits_unit_sphere() {
+        let sphere = "c200: 1, c020: 1, c002: 1, c000: -1";
+        let quadric: Quadric = json5::from_str(&format!("{{{sphere}}}")).unwrap();
+        let quadratic: Quadratic = json5::from_str(&format!("{{{sphere}}}")).unwrap();
+        let quartic = json5::from_str::<DeserializableQuartic>(&format!("{{{sphere}}}"))
+            .unwrap()
+            .into_rt_model();
+
+        // Through the center, and off it by 0.6 where the chord is 2 * 0.8 long
+        let through = ray([0.0, 0.0, -5.0], [0.0, 0.0, 1.0]);
+        let off = ray([0.6, 0.0, -5.0], [0.0, 0.0, 1.0]);
+        let models: [&dyn RTModel; 3] = [&quadric, &quadratic, quartic.as_ref()];
+        for model in models {
+            let hits: Vec<f64> = model.test(through).iter().map(|h| h.distance).collect();
+            assert_roots(&hits, &[4.0, 6.0]);
+            let hits: Vec<f64> = model.test(off).iter().map(|h| h.distance).collect();
+            assert_roots(&hits, &[4.2, 5.8]);
+        }
+        assert_distances(&quadric, ray([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]), &[0.0, 1.0]);
+    }
+
+    #[test]
+    fn hits_torus() {
+        // (x^2 + y^2 + z^2 + R^2 - r^2)^2 - 4 R^2 (x^2 + y^2) with R = 2 and r = 0.5
+        let torus = json5::from_str::<DeserializableQuartic>(
+            "{
+                c400: 1, c040: 1, c004: 1, c220: 2, c022: 2, c202: 2,
+                c200: -8.5, c020: -8.5, c002: 7.5, c000: 14.0625,
+            }",
+        )
+        .unwrap()
+        .into_rt_model();
+
+        assert_distances(
+            torus.as_ref(),
+            ray([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]),
+            &[2.5, 3.5, 6.5, 7.5],
+        );
+        // 0.3 above the ring, the tube is 2 * 0.4 wide
+        assert_distances(
+            torus.as_ref(),
+            ray([-5.0, 0.0, 0.3], [1.0, 0.0, 0.0]),
+            &[2.6, 3.4, 6.6, 7.4],
+        );
+        // Down through the tube, and grazing its top
+        assert_distances(
+            torus.as_ref(),
+            ray([2.0, 0.0, 5.0], [0.0, 0.0, -1.0]),
+            &[4.5, 5.5],
+        );
+        assert_distances(
+            torus.as_ref(),
+            ray([-5.0, 0.0, 0.5], [1.0, 0.0, 0.0]),
+            &[3.0, 3.0, 7.0, 7.0],
+        );
+    }
+}
@@ -4,18 +4,19 @@ use crate::{
     deserialize::{deserialize_ldr_float, deserialize_nonnegative_float},
     json_schema::{LDRColorSchema, PositionSchema},
     texture::{DeserializableTexture, Texture},
-    ImageCache, ImageLoader,
+    BuildContext, ImageLoader, MeshLoader,
 };
 
 use super::{
     super::super::deserialize::{deserialize_ldr_color, deserialize_position},
+    sdf::{Sdf, SdfMaterial},
     util::one,
     Hit, RTModel,
 };
 use schemars::JsonSchema;
 use serde::Deserialize;
 use seui_engine_raytracing_csg_renderer_core::types::{
-    math::{Direction, Position, Vec3},
+    math::{Aabb, Direction, Position, Vec3},
     rt::Ray,
 };
 use seui_engine_raytracing_csg_renderer_types::LDRColor;
@@ -43,9 +44,9 @@ pub struct DeserializableSphere {
 }
 
 impl DeserializableSphere {
-    pub fn into_rt_model<T: ImageLoader>(
+    pub fn into_rt_model<T: ImageLoader, M: MeshLoader>(
         self,
-        image_cache: &mut ImageCache<T>,
+        context: &mut BuildContext<T, M>,
     ) -> Box<dyn RTModel + Send + Sync> {
         Box::new(Sphere {
             radius: self.radius,
@@ -53,7 +54,21 @@ impl DeserializableSphere {
             albedo: self.albedo,
             roughness: self.roughness,
             metallic: self.metallic,
-            texture: self.texture.map(|t| t.into_texture(image_cache)),
+            texture: self
+                .texture
+                .map(|t| t.into_texture(&mut context.image_cache)),
+        })
+    }
+
+    /// Distance field of the sphere, ignoring its texture.
+    pub fn into_sdf(self) -> Box<dyn Sdf + Send + Sync> {
+        Box::new(Sphere {
+            radius: self.radius,
+            position: self.position,
+            albedo: self.albedo,
+            roughness: self.roughness,
+            metallic: self.metallic,
+            texture: None,
         })
     }
 }
@@ -152,4 +167,27 @@ impl RTModel for Sphere {
 
         result
     }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(*self.position - extent, *self.position + extent))
+    }
+}
+
+impl Sdf for Sphere {
+    fn distance(&self, position: Vec3) -> f64 {
+        (position - *self.position).length() - self.radius
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        RTModel::bounds(self)
+    }
+
+    fn material(&self, _position: Vec3) -> Option<SdfMaterial> {
+        Some(SdfMaterial {
+            albedo: self.albedo,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        })
+    }
 }
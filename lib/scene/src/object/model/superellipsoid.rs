@@ -0,0 +1,309 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Direction, Position, Vec3},
+    rt::Ray,
+};
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+use crate::{
+    deserialize::{
+        deserialize_ldr_color, deserialize_ldr_float, deserialize_position,
+        deserialize_positive_float, deserialize_scale,
+    },
+    json_schema::{float_positive, LDRColorSchema, PositionSchema, Scale},
+};
+
+use super::{util::one, Hit, RTModel};
+
+/// Exponents below this turn the field's powers into overflows
+const MIN_EXPONENT: f64 = 0.01;
+/// Halvings of the part of the ray in the bounding box, enough to reach the rounding error
+const MAX_DEPTH: u32 = 60;
+
+/// `(|x/rx|^(2/e2) + |y/ry|^(2/e2))^(e2/e1) + |z/rz|^(2/e1) = 1`.
+///
+/// Exponents near 0 give boxes, 1 ellipsoids, 2 octahedra and larger values pinched stars;
+/// `e1` shapes the profile along Z and `e2` the cross-section.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableSuperellipsoid {
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    #[serde(default, deserialize_with = "deserialize_scale")]
+    radii: Scale,
+    #[serde(default = "one", deserialize_with = "deserialize_positive_float")]
+    #[schemars(schema_with = "float_positive")]
+    e1: f64,
+    #[serde(default = "one", deserialize_with = "deserialize_positive_float")]
+    #[schemars(schema_with = "float_positive")]
+    e2: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_color")]
+    #[schemars(with = "LDRColorSchema")]
+    albedo: LDRColor,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: f64,
+}
+
+impl DeserializableSuperellipsoid {
+    pub fn into_rt_model(self) -> Box<dyn RTModel + Send + Sync> {
+        if self.radii.x <= 0.0 || self.radii.y <= 0.0 || self.radii.z <= 0.0 {
+            panic!("Superellipsoid radii must be positive");
+        }
+        if self.e1 < MIN_EXPONENT || self.e2 < MIN_EXPONENT {
+            panic!("Superellipsoid exponents must be at least {}", MIN_EXPONENT);
+        }
+        Box::new(Superellipsoid {
+            position: self.position,
+            radii: Vec3::new(self.radii.x, self.radii.y, self.radii.z),
+            e1: self.e1,
+            e2: self.e2,
+            albedo: self.albedo,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        })
+    }
+}
+
+struct Superellipsoid {
+    position: Position,
+    radii: Vec3,
+    e1: f64,
+    e2: f64,
+    albedo: LDRColor,
+    roughness: f64,
+    metallic: f64,
+}
+
+impl Superellipsoid {
+    fn hit(&self, distance: f64, normal: Direction, is_front_face: bool) -> Hit {
+        Hit {
+            distance,
+            normal,
+            albedo: self.albedo,
+            is_front_face,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        }
+    }
+
+    /// Field with the surface at 0, negative inside, raised to `e1 / 2`
+    /// so that it grows about linearly with the distance from the center.
+    fn value(&self, p: Vec3) -> f64 {
+        let x = (p.x / self.radii.x).abs().powf(2.0 / self.e2);
+        let y = (p.y / self.radii.y).abs().powf(2.0 / self.e2);
+        let z = (p.z / self.radii.z).abs().powf(2.0 / self.e1);
+        ((x + y).powf(self.e2 / self.e1) + z).powf(self.e1 / 2.0) - 1.0
+    }
+
+    /// Outward normal from the gradient of the field.
+    fn normal(&self, p: Vec3) -> Direction {
+        // d/dx |x/r|^k = k |x/r|^(k - 1) sign(x) / r, kept finite on the axes
+        let power_derivative = |v: f64, r: f64, k: f64| {
+            if v == 0.0 {
+                0.0
+            } else {
+                k * (v / r).abs().powf(k - 1.0) * v.signum() / r
+            }
+        };
+        let k2 = 2.0 / self.e2;
+        let k1 = 2.0 / self.e1;
+        let xy = (p.x / self.radii.x).abs().powf(k2) + (p.y / self.radii.y).abs().powf(k2);
+        let xy_factor = if xy > 0.0 {
+            (self.e2 / self.e1) * xy.powf(self.e2 / self.e1 - 1.0)
+        } else {
+            0.0
+        };
+        Direction::new(Vec3::new(
+            xy_factor * power_derivative(p.x, self.radii.x, k2),
+            xy_factor * power_derivative(p.y, self.radii.y, k2),
+            power_derivative(p.z, self.radii.z, k1),
+        ))
+    }
+
+    /// Bisects a sign change of the field between `lo` and `hi`.
+    fn refine(&self, origin: Vec3, direction: Vec3, mut lo: f64, mut hi: f64) -> f64 {
+        let lo_is_inside = self.value(origin + direction * lo) < 0.0;
+        for _ in 0..MAX_DEPTH {
+            let middle = 0.5 * (lo + hi);
+            if (self.value(origin + direction * middle) < 0.0) == lo_is_inside {
+                lo = middle;
+            } else {
+                hi = middle;
+            }
+        }
+        0.5 * (lo + hi)
+    }
+
+    /// Distances in `[enter, exit]` where the ray crosses the surface.
+    fn crossings(&self, origin: Vec3, direction: Vec3, enter: f64, exit: f64) -> Vec<f64> {
+        let mut crossings = Vec::new();
+        let inside = |t: f64| self.value(origin + direction * t) < 0.0;
+        if self.e1 <= 2.0 && self.e2 <= 2.0 {
+            // The field plus one is then a norm, convex along the ray, so it
+            // falls to its minimum and rises after it, crossing zero at most once each way
+            let lowest = self.lowest(origin, direction, enter, exit);
+            if !inside(lowest) {
+                return crossings;
+            }
+            if !inside(enter) {
+                crossings.push(self.refine(origin, direction, enter, lowest));
+            }
+            if !inside(exit) {
+                crossings.push(self.refine(origin, direction, lowest, exit));
+            }
+        } else {
+            self.subdivide(
+                origin,
+                direction,
+                (enter, inside(enter)),
+                (exit, inside(exit)),
+                0,
+                &mut crossings,
+            );
+        }
+        crossings
+    }
+
+    /// Minimum of the convex field along the ray, by golden section search.
+    fn lowest(&self, origin: Vec3, direction: Vec3, mut lo: f64, mut hi: f64) -> f64 {
+        let ratio = 0.5 * (5f64.sqrt() - 1.0);
+        let value = |t: f64| self.value(origin + direction * t);
+        let mut a = hi - ratio * (hi - lo);
+        let mut b = lo + ratio * (hi - lo);
+        let (mut value_a, mut value_b) = (value(a), value(b));
+        for _ in 0..2 * MAX_DEPTH {
+            if value_a < value_b {
+                hi = b;
+                (b, value_b) = (a, value_a);
+                a = hi - ratio * (hi - lo);
+                value_a = value(a);
+            } else {
+                lo = a;
+                (a, value_a) = (b, value_b);
+                b = lo + ratio * (hi - lo);
+                value_b = value(b);
+            }
+        }
+        0.5 * (lo + hi)
+    }
+
+    /// Lowest and highest values of the field over the ray between `t0` and `t1`.
+    ///
+    /// The field grows with the distance from each of the three planes of symmetry,
+    /// so the points of the segment nearest to and furthest from them bound it.
+    fn range(&self, origin: Vec3, direction: Vec3, t0: f64, t1: f64) -> (f64, f64) {
+        let (start, end) = (origin + direction * t0, origin + direction * t1);
+        let span = |a: f64, b: f64| {
+            let nearest = if (a <= 0.0) != (b <= 0.0) {
+                0.0
+            } else {
+                a.abs().min(b.abs())
+            };
+            (nearest, a.abs().max(b.abs()))
+        };
+        let (x, y, z) = (
+            span(start.x, end.x),
+            span(start.y, end.y),
+            span(start.z, end.z),
+        );
+        (
+            self.value(Vec3::new(x.0, y.0, z.0)),
+            self.value(Vec3::new(x.1, y.1, z.1)),
+        )
+    }
+
+    /// Splits the ray until each piece is either known to stay on one side of the surface
+    /// or small enough to bisect, so that no thin chord or spike falls between samples.
+    fn subdivide(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        (t0, inside0): (f64, bool),
+        (t1, inside1): (f64, bool),
+        depth: u32,
+        crossings: &mut Vec<f64>,
+    ) {
+        let (lowest, highest) = self.range(origin, direction, t0, t1);
+        if lowest >= 0.0 || highest < 0.0 {
+            return;
+        }
+        if depth == MAX_DEPTH {
+            // Narrower than rounding, a piece with both ends on the same side is grazed
+            if inside0 != inside1 {
+                crossings.push(0.5 * (t0 + t1));
+            }
+            return;
+        }
+        let middle = 0.5 * (t0 + t1);
+        let middle = (middle, self.value(origin + direction * middle) < 0.0);
+        self.subdivide(
+            origin,
+            direction,
+            (t0, inside0),
+            middle,
+            depth + 1,
+            crossings,
+        );
+        self.subdivide(
+            origin,
+            direction,
+            middle,
+            (t1, inside1),
+            depth + 1,
+            crossings,
+        );
+    }
+}
+
+impl RTModel for Superellipsoid {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let mut result = Vec::new();
+
+        let origin = *(ray.origin - self.position);
+        let direction: Vec3 = *ray.direction;
+        let inverse_direction = Vec3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z);
+        let (enter, exit) = Aabb::new(-self.radii, self.radii).hit_range(origin, inverse_direction);
+        if enter > exit || exit <= 0.0 {
+            return result;
+        }
+        let enter = enter.max(0.0);
+
+        let mut is_front_face = false;
+        if self.value(origin + direction * enter) < 0.0 {
+            is_front_face = true;
+            let normal = if enter == 0.0 {
+                -ray.direction
+            } else {
+                self.normal(origin + direction * enter)
+            };
+            result.push(self.hit(enter, normal, true));
+        }
+        for crossing in self.crossings(origin, direction, enter, exit) {
+            is_front_face = !is_front_face;
+            result.push(self.hit(
+                crossing,
+                self.normal(origin + direction * crossing),
+                is_front_face,
+            ));
+        }
+        if is_front_face {
+            // Box shaped superellipsoids reach their bounding box
+            result.push(self.hit(exit, self.normal(origin + direction * exit), false));
+        }
+
+        result
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::new(
+            *self.position - self.radii,
+            *self.position + self.radii,
+        ))
+    }
+}
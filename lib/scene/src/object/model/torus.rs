@@ -0,0 +1,222 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Direction, Position, Vec3},
+    rt::Ray,
+};
+use seui_engine_raytracing_csg_renderer_types::LDRColor;
+
+use crate::{
+    deserialize::{
+        deserialize_direction, deserialize_ldr_color, deserialize_ldr_float,
+        deserialize_nonnegative_float, deserialize_position,
+    },
+    json_schema::{DirectionSchema, LDRColorSchema, PositionSchema},
+};
+
+use super::{
+    sdf::{Sdf, SdfMaterial},
+    solver::roots_in_range,
+    util::{one, orthonormal_basis, up},
+    Hit, RTModel,
+};
+
+fn quarter() -> f64 {
+    0.25
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableTorus {
+    #[serde(default, deserialize_with = "deserialize_position")]
+    #[schemars(with = "PositionSchema")]
+    position: Position,
+    #[serde(default = "up", deserialize_with = "deserialize_direction")]
+    #[schemars(with = "DirectionSchema")]
+    axis: Direction,
+    /// Distance from the center to the center of the tube
+    #[serde(default = "one", deserialize_with = "deserialize_nonnegative_float")]
+    #[schemars(range(min = 0))]
+    major_radius: f64,
+    /// Radius of the tube
+    #[serde(
+        default = "quarter",
+        deserialize_with = "deserialize_nonnegative_float"
+    )]
+    #[schemars(range(min = 0))]
+    minor_radius: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_color")]
+    #[schemars(with = "LDRColorSchema")]
+    albedo: LDRColor,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    roughness: f64,
+    #[serde(default, deserialize_with = "deserialize_ldr_float")]
+    #[schemars(range(min = 0, max = 1))]
+    metallic: f64,
+}
+
+impl DeserializableTorus {
+    pub fn into_rt_model(self) -> Box<dyn RTModel + Send + Sync> {
+        Box::new(self.into_torus())
+    }
+
+    pub fn into_sdf(self) -> Box<dyn Sdf + Send + Sync> {
+        Box::new(self.into_torus())
+    }
+
+    fn into_torus(self) -> Torus {
+        let (u, v) = orthonormal_basis(self.axis);
+        Torus {
+            position: self.position,
+            u,
+            v,
+            axis: *self.axis,
+            major_radius: self.major_radius,
+            minor_radius: self.minor_radius,
+            albedo: self.albedo,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        }
+    }
+}
+
+struct Torus {
+    position: Position,
+    u: Vec3,
+    v: Vec3,
+    axis: Vec3,
+    major_radius: f64,
+    minor_radius: f64,
+    albedo: LDRColor,
+    roughness: f64,
+    metallic: f64,
+}
+
+impl Torus {
+    fn hit(&self, distance: f64, normal: Direction, is_front_face: bool) -> Hit {
+        Hit {
+            distance,
+            normal,
+            albedo: self.albedo,
+            is_front_face,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        }
+    }
+
+    /// Implicit function, negative inside the tube.
+    fn value(&self, x: f64, y: f64, z: f64) -> f64 {
+        let r2 = self.major_radius * self.major_radius;
+        let k = x * x + y * y + z * z + r2 - self.minor_radius * self.minor_radius;
+        k * k - 4.0 * r2 * (x * x + y * y)
+    }
+
+    /// Outward normal in world space from the gradient of the implicit function.
+    fn normal(&self, x: f64, y: f64, z: f64) -> Direction {
+        let r2 = self.major_radius * self.major_radius;
+        let k = x * x + y * y + z * z + r2 - self.minor_radius * self.minor_radius;
+        let gx = 4.0 * k * x - 8.0 * r2 * x;
+        let gy = 4.0 * k * y - 8.0 * r2 * y;
+        let gz = 4.0 * k * z;
+        Direction::new(self.u * gx + self.v * gy + self.axis * gz)
+    }
+}
+
+impl RTModel for Torus {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let mut result = Vec::new();
+
+        let origin = *(ray.origin - self.position);
+        let direction: Vec3 = *ray.direction;
+        let (ox, oy, oz) = (
+            origin.dot(self.u),
+            origin.dot(self.v),
+            origin.dot(self.axis),
+        );
+        let (dx, dy, dz) = (
+            direction.dot(self.u),
+            direction.dot(self.v),
+            direction.dot(self.axis),
+        );
+
+        // Clip the ray to the bounding sphere and restart it there, which keeps
+        // the quartic's coefficients small for distant rays
+        let bound = self.major_radius + self.minor_radius;
+        let b = ox * dx + oy * dy + oz * dz;
+        let c = origin.length_square() - bound * bound;
+        let discriminant = b * b - c;
+        if discriminant <= 0.0 {
+            return result;
+        }
+        let t_exit = -b + discriminant.sqrt();
+        if t_exit <= 0.0 {
+            return result;
+        }
+        let t_start = (-b - discriminant.sqrt()).max(0.0);
+
+        let (px, py, pz) = (ox + t_start * dx, oy + t_start * dy, oz + t_start * dz);
+        let r2 = self.major_radius * self.major_radius;
+        let n = px * dx + py * dy + pz * dz;
+        let q = px * px + py * py + pz * pz + r2 - self.minor_radius * self.minor_radius;
+        let coefficients = [
+            q * q - 4.0 * r2 * (px * px + py * py),
+            4.0 * n * q - 8.0 * r2 * (px * dx + py * dy),
+            4.0 * n * n + 2.0 * q - 4.0 * r2 * (dx * dx + dy * dy),
+            4.0 * n,
+            1.0,
+        ];
+
+        let mut is_front_face = false;
+        if self.value(ox, oy, oz) < 0.0 {
+            is_front_face = true;
+            result.push(self.hit(0.0, -ray.direction, true));
+        }
+        for s in roots_in_range(&coefficients, 0.0, t_exit - t_start) {
+            let t = t_start + s;
+            if t <= 0.0 {
+                continue;
+            }
+            is_front_face = !is_front_face;
+            let normal = self.normal(ox + t * dx, oy + t * dy, oz + t * dz);
+            result.push(self.hit(t, normal, is_front_face));
+        }
+        if is_front_face {
+            // Lost a crossing to round-off, close the solid where the bounding sphere ends
+            result.push(self.hit(t_exit, ray.direction, false));
+        }
+
+        result
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let extent = |a: f64| self.major_radius * (1.0 - a * a).max(0.0).sqrt() + self.minor_radius;
+        let extent = Vec3::new(
+            extent(self.axis.x),
+            extent(self.axis.y),
+            extent(self.axis.z),
+        );
+        Some(Aabb::new(*self.position - extent, *self.position + extent))
+    }
+}
+
+impl Sdf for Torus {
+    fn distance(&self, position: Vec3) -> f64 {
+        let p = position - *self.position;
+        let (x, y, z) = (p.dot(self.u), p.dot(self.v), p.dot(self.axis));
+        let ring = (x * x + y * y).sqrt() - self.major_radius;
+        (ring * ring + z * z).sqrt() - self.minor_radius
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        RTModel::bounds(self)
+    }
+
+    fn material(&self, _position: Vec3) -> Option<SdfMaterial> {
+        Some(SdfMaterial {
+            albedo: self.albedo,
+            roughness: self.roughness,
+            metallic: self.metallic,
+        })
+    }
+}
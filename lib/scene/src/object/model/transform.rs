@@ -0,0 +1,385 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Direction, Mat4, Move, Position, Vec3},
+    rt::Ray,
+};
+
+use crate::{
+    deserialize::{deserialize_direction, deserialize_move, deserialize_scale},
+    json_schema::{DirectionSchema, MoveSchema, Scale},
+    BuildContext, ImageLoader, MeshLoader,
+};
+
+use super::{
+    sdf::{Sdf, SdfMaterial},
+    util::{one, up},
+    DeserializableRTModel, Hit, RTModel,
+};
+
+/// Angles in degrees, applied around X first, then Y, then Z.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct EulerRotation {
+    #[serde(default)]
+    x: f64,
+    #[serde(default)]
+    y: f64,
+    #[serde(default)]
+    z: f64,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AxisAngleRotation {
+    #[serde(default = "up", deserialize_with = "deserialize_direction")]
+    #[schemars(with = "DirectionSchema")]
+    axis: Direction,
+    /// Angle in degrees
+    angle: f64,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct QuaternionRotation {
+    #[serde(default)]
+    x: f64,
+    #[serde(default)]
+    y: f64,
+    #[serde(default)]
+    z: f64,
+    #[serde(default = "one")]
+    w: f64,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "camelCase", deny_unknown_fields)]
+pub enum Rotation {
+    Euler(EulerRotation),
+    AxisAngle(AxisAngleRotation),
+    Quaternion(QuaternionRotation),
+}
+
+/// Product of the quaternions `[x, y, z, w]`, rotating by `b` first and then by `a`.
+fn quaternion_product(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+    let [ax, ay, az, aw] = a;
+    let [bx, by, bz, bw] = b;
+    [
+        aw * bx + ax * bw + ay * bz - az * by,
+        aw * by - ax * bz + ay * bw + az * bx,
+        aw * bz + ax * by - ay * bx + az * bw,
+        aw * bw - ax * bx - ay * by - az * bz,
+    ]
+}
+
+fn axis_angle_quaternion(axis: Vec3, angle: f64) -> [f64; 4] {
+    let axis = axis.normalize();
+    let (s, c) = (angle / 2.0).sin_cos();
+    [axis.x * s, axis.y * s, axis.z * s, c]
+}
+
+impl Rotation {
+    /// Unit quaternion `[x, y, z, w]` of the same rotation as [`Rotation::matrix`].
+    pub fn quaternion(&self) -> [f64; 4] {
+        match self {
+            Rotation::Euler(r) => quaternion_product(
+                axis_angle_quaternion(Vec3::Z, r.z.to_radians()),
+                quaternion_product(
+                    axis_angle_quaternion(Vec3::Y, r.y.to_radians()),
+                    axis_angle_quaternion(Vec3::X, r.x.to_radians()),
+                ),
+            ),
+            Rotation::AxisAngle(r) => axis_angle_quaternion(*r.axis, r.angle.to_radians()),
+            Rotation::Quaternion(r) => {
+                let length = (r.x * r.x + r.y * r.y + r.z * r.z + r.w * r.w).sqrt();
+                if length < 1e-12 {
+                    return [0.0, 0.0, 0.0, 1.0];
+                }
+                [r.x, r.y, r.z, r.w].map(|c| c / length)
+            }
+        }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        match self {
+            Rotation::Euler(r) => {
+                Mat4::rotation(Vec3::Z, r.z.to_radians())
+                    * Mat4::rotation(Vec3::Y, r.y.to_radians())
+                    * Mat4::rotation(Vec3::X, r.x.to_radians())
+            }
+            Rotation::AxisAngle(r) => Mat4::rotation(*r.axis, r.angle.to_radians()),
+            Rotation::Quaternion(r) => Mat4::quaternion(r.x, r.y, r.z, r.w),
+        }
+    }
+}
+
+/// Each component moves the first axis proportionally to the second one,
+/// e.g. `xy` adds `xy * y` to `x`.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Shear {
+    #[serde(default)]
+    xy: f64,
+    #[serde(default)]
+    xz: f64,
+    #[serde(default)]
+    yx: f64,
+    #[serde(default)]
+    yz: f64,
+    #[serde(default)]
+    zx: f64,
+    #[serde(default)]
+    zy: f64,
+}
+
+impl Shear {
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::new([
+            [1.0, self.xy, self.xz, 0.0],
+            [self.yx, 1.0, self.yz, 0.0],
+            [self.zx, self.zy, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+}
+
+/// The raw `matrix` is applied first, followed by `scale`, `shear`, `rotation` and `translation`.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeserializableTransform {
+    model: Box<DeserializableRTModel>,
+    #[serde(default, deserialize_with = "deserialize_move")]
+    #[schemars(with = "MoveSchema")]
+    translation: Move,
+    #[serde(default)]
+    rotation: Option<Rotation>,
+    #[serde(default, deserialize_with = "deserialize_scale")]
+    scale: Scale,
+    #[serde(default)]
+    shear: Shear,
+    /// Rows of an affine matrix, the last of which must be [0, 0, 0, 1]
+    #[serde(default)]
+    matrix: Option<[[f64; 4]; 4]>,
+}
+
+pub fn affine_matrix(
+    translation: Move,
+    rotation: Option<&Rotation>,
+    shear: &Shear,
+    scale: Scale,
+    matrix: Option<[[f64; 4]; 4]>,
+) -> Mat4 {
+    if matrix.is_some_and(|m| m[3] != [0.0, 0.0, 0.0, 1.0]) {
+        panic!("A transform matrix must be affine, with a bottom row of [0, 0, 0, 1]");
+    }
+    Mat4::translation(*translation)
+        * rotation.map(Rotation::matrix).unwrap_or_default()
+        * shear.matrix()
+        * Mat4::scale(Vec3::new(scale.x, scale.y, scale.z))
+        * matrix.map(Mat4::new).unwrap_or_default()
+}
+
+impl DeserializableTransform {
+    pub fn into_rt_model<T: ImageLoader, M: MeshLoader>(
+        self,
+        context: &mut BuildContext<T, M>,
+    ) -> Box<dyn RTModel + Send + Sync> {
+        let matrix = self.affine_matrix();
+        Box::new(Transform::new(self.model.into_rt_model(context), matrix))
+    }
+
+    pub fn into_sdf<T: ImageLoader, M: MeshLoader>(
+        self,
+        context: &mut BuildContext<T, M>,
+    ) -> Box<dyn Sdf + Send + Sync> {
+        let matrix = self.affine_matrix();
+        Box::new(TransformSdf::new(self.model.into_sdf(context), matrix))
+    }
+
+    fn affine_matrix(&self) -> Mat4 {
+        affine_matrix(
+            self.translation,
+            self.rotation.as_ref(),
+            &self.shear,
+            self.scale,
+            self.matrix,
+        )
+    }
+}
+
+pub struct Transform {
+    model: Box<dyn RTModel + Send + Sync>,
+    matrix: Mat4,
+    inverse: Mat4,
+    normal_matrix: Mat4,
+}
+
+impl Transform {
+    /// `matrix` maps the model's local space into the parent space.
+    pub fn new(model: Box<dyn RTModel + Send + Sync>, matrix: Mat4) -> Transform {
+        let inverse = matrix
+            .inverse()
+            .expect("Transform matrix must be invertible");
+        Transform {
+            model,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+        }
+    }
+}
+
+/// Hits of `model` placed in the parent space by the inverse of `inverse`,
+/// whose transpose `normal_matrix` is.
+pub fn transformed_test(
+    model: &(dyn RTModel + Send + Sync),
+    inverse: &Mat4,
+    normal_matrix: &Mat4,
+    ray: Ray,
+) -> Vec<Hit> {
+    let direction = inverse.transform_vector(*ray.direction);
+    // Local distances are measured along the normalized local direction
+    let scale = direction.length();
+    let local_ray = Ray {
+        origin: Position::new(inverse.transform_position(*ray.origin)),
+        direction: Direction::new(direction),
+        time: ray.time,
+    };
+
+    model
+        .test(local_ray)
+        .into_iter()
+        .map(|hit| Hit {
+            distance: hit.distance / scale,
+            normal: Direction::new(normal_matrix.transform_vector(*hit.normal)),
+            ..hit
+        })
+        .collect()
+}
+
+impl RTModel for Transform {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        transformed_test(self.model.as_ref(), &self.inverse, &self.normal_matrix, ray)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let bounds = self.model.bounds()?;
+        if bounds.is_empty() {
+            return Some(bounds);
+        }
+        Some(Aabb::from_points(
+            bounds
+                .corners()
+                .map(|corner| self.matrix.transform_position(corner)),
+        ))
+    }
+}
+
+/// Greatest factor by which the linear part of `matrix` stretches a vector, its largest singular
+/// value. Under shear it exceeds the length of every transformed axis.
+fn spectral_norm(matrix: &Mat4) -> f64 {
+    // Largest eigenvalue of the symmetric M^T M, in closed form
+    let m = matrix.m;
+    let a: [[f64; 3]; 3] =
+        std::array::from_fn(|i| std::array::from_fn(|j| (0..3).map(|k| m[k][i] * m[k][j]).sum()));
+    let off_diagonal = a[0][1] * a[0][1] + a[0][2] * a[0][2] + a[1][2] * a[1][2];
+    if off_diagonal == 0.0 {
+        return a[0][0].max(a[1][1]).max(a[2][2]).sqrt();
+    }
+    let q = (a[0][0] + a[1][1] + a[2][2]) / 3.0;
+    let p = (((a[0][0] - q).powi(2)
+        + (a[1][1] - q).powi(2)
+        + (a[2][2] - q).powi(2)
+        + 2.0 * off_diagonal)
+        / 6.0)
+        .sqrt();
+    let b: [[f64; 3]; 3] = std::array::from_fn(|i| {
+        std::array::from_fn(|j| (a[i][j] - if i == j { q } else { 0.0 }) / p)
+    });
+    let determinant = b[0][0] * (b[1][1] * b[2][2] - b[1][2] * b[2][1])
+        - b[0][1] * (b[1][0] * b[2][2] - b[1][2] * b[2][0])
+        + b[0][2] * (b[1][0] * b[2][1] - b[1][1] * b[2][0]);
+    let angle = (determinant / 2.0).clamp(-1.0, 1.0).acos() / 3.0;
+    (q + 2.0 * p * angle.cos()).max(0.0).sqrt()
+}
+
+/// Distance field seen through an affine transform.
+///
+/// Distances are exact for rigid motions and uniform scales, and scaled down by
+/// the least stretch of the linear part otherwise.
+struct TransformSdf {
+    sdf: Box<dyn Sdf + Send + Sync>,
+    matrix: Mat4,
+    inverse: Mat4,
+    min_stretch: f64,
+}
+
+impl TransformSdf {
+    fn new(sdf: Box<dyn Sdf + Send + Sync>, matrix: Mat4) -> TransformSdf {
+        let inverse = matrix
+            .inverse()
+            .expect("Transform matrix must be invertible");
+        TransformSdf {
+            sdf,
+            matrix,
+            inverse,
+            min_stretch: 1.0 / spectral_norm(&inverse),
+        }
+    }
+}
+
+impl Sdf for TransformSdf {
+    fn distance(&self, position: Vec3) -> f64 {
+        self.sdf.distance(self.inverse.transform_position(position)) * self.min_stretch
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let bounds = self.sdf.bounds()?;
+        if bounds.is_empty() {
+            return Some(bounds);
+        }
+        Some(Aabb::from_points(
+            bounds
+                .corners()
+                .map(|corner| self.matrix.transform_position(corner)),
+        ))
+    }
+
+    fn lipschitz(&self) -> f64 {
+        // Scaling by the least stretch undoes the inverse's greatest one
+        self.sdf.lipschitz()
+    }
+
+    fn material(&self, position: Vec3) -> Option<SdfMaterial> {
+        self.sdf.material(self.inverse.transform_position(position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use seui_engine_raytracing_csg_renderer_core::types::math::{Mat4, Vec3};
+
+    use super::spectral_norm;
+
+    #[test]
+    fn spectral_norm_of_shear_exceeds_axes() {
+        let shear = Mat4::new([
+            [1.0, 2.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        assert!((spectral_norm(&shear) - (1.0 + 2f64.sqrt())).abs() < 1e-12);
+        let inverse = shear.inverse().unwrap();
+        assert!((1.0 / spectral_norm(&inverse) - (2f64.sqrt() - 1.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn spectral_norm_of_rotated_scale() {
+        let matrix = Mat4::translation(Vec3::new(5.0, -1.0, 2.0))
+            * Mat4::rotation(Vec3::new(1.0, 2.0, 3.0), 0.7)
+            * Mat4::scale(Vec3::new(0.5, 3.0, 2.0));
+        assert!((spectral_norm(&matrix) - 3.0).abs() < 1e-12);
+        assert!((1.0 / spectral_norm(&matrix.inverse().unwrap()) - 0.5).abs() < 1e-12);
+    }
+}
@@ -1,4 +1,7 @@
-use seui_engine_raytracing_csg_renderer_core::types::math::Direction;
+use seui_engine_raytracing_csg_renderer_core::types::{
+    math::{Aabb, Direction, Vec3},
+    rt::Ray,
+};
 
 pub fn enhance_normal(
     ray_direction: Direction,
@@ -12,6 +15,37 @@ pub fn enhance_normal(
     }
 }
 
+/// Cheap slab test, whether the ray passes through the box ahead of its origin.
+pub fn ray_hits_bounds(bounds: &Aabb, ray: Ray) -> bool {
+    let direction: Vec3 = *ray.direction;
+    let inverse_direction = Vec3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z);
+    let (enter, exit) = bounds.hit_range(*ray.origin, inverse_direction);
+    enter <= exit && exit >= 0.0
+}
+
+/// Two unit vectors perpendicular to `axis` and to each other, such that `(u, v, axis)` is right-handed.
+pub fn orthonormal_basis(axis: Direction) -> (Vec3, Vec3) {
+    let helper = if axis.x.abs() < 0.9 { Vec3::X } else { Vec3::Y };
+    let u = helper.cross(*axis).normalize();
+    let v = axis.cross(u);
+    (u, v)
+}
+
+/// Box around a circle of `radius` perpendicular to `axis`.
+pub fn disk_bounds(center: Vec3, axis: Direction, radius: f64) -> Aabb {
+    // A circle of radius r around an axis a reaches r * sqrt(1 - a_i^2) along axis i
+    let extent = Vec3::new(
+        radius * (1.0 - axis.x * axis.x).max(0.0).sqrt(),
+        radius * (1.0 - axis.y * axis.y).max(0.0).sqrt(),
+        radius * (1.0 - axis.z * axis.z).max(0.0).sqrt(),
+    );
+    Aabb::new(center - extent, center + extent)
+}
+
+pub fn up() -> Direction {
+    Direction::new(Vec3::Z)
+}
+
 pub fn one() -> f64 {
     1.0
 }